@@ -0,0 +1,116 @@
+//! 无界面的 Chatless MCP 服务器：通过 stdio 提供文档解析、网页搜索/抓取与嵌入工具，
+//! 供其他 MCP 客户端直接作为子进程拉起，不会启动 Tauri 窗口。
+//!
+//! 注意：stdout 专用于 MCP 协议，诊断信息一律写到 stderr。
+
+use chatless_lib::mcp::local_server::{LocalServer, LocalServerOptions};
+use chatless_lib::onnx_logic::OnnxState;
+use clap::Parser;
+use ort::session::Session;
+use rmcp::{transport::stdio, ServiceExt};
+use std::sync::{Arc, Mutex};
+
+#[derive(Parser, Debug)]
+#[command(
+  name = "chatless-mcp",
+  version,
+  about = "Serve Chatless tools (document parsing, web search/fetch, embeddings) over MCP"
+)]
+struct Cli {
+  /// 通过 stdin/stdout 提供 MCP 服务（目前唯一支持的模式）
+  #[arg(long)]
+  stdio: bool,
+
+  /// 搜索提供商：google | bing | ollama | duckduckgo | custom_scrape
+  #[arg(long, default_value = "duckduckgo")]
+  search_provider: String,
+
+  /// 搜索提供商的 API Key；未指定时读取环境变量 CHATLESS_SEARCH_API_KEY
+  #[arg(long)]
+  search_api_key: Option<String>,
+
+  /// Google 的 CSE ID
+  #[arg(long)]
+  search_cse_id: Option<String>,
+
+  /// 嵌入模型（.onnx）路径；与 --tokenizer 一起指定后启用 generate_embeddings
+  #[arg(long, requires = "tokenizer")]
+  model: Option<String>,
+
+  /// 嵌入模型对应的 tokenizer.json 路径
+  #[arg(long, requires = "model")]
+  tokenizer: Option<String>,
+
+  /// 分词最大长度
+  #[arg(long, default_value_t = 512)]
+  max_length: usize,
+
+  /// parse_document 可读取的目录，可重复指定；未指定时该工具不可用
+  #[arg(long = "allow-dir", value_name = "DIR")]
+  allow_dirs: Vec<String>,
+
+  /// ONNX Runtime 动态库路径（未指定时按 ORT_DYLIB_PATH 或系统默认位置加载）
+  #[arg(long)]
+  onnx_runtime: Option<String>,
+}
+
+fn load_session(cli: &Cli) -> Result<Option<Session>, String> {
+  let Some(model) = &cli.model else {
+    return Ok(None);
+  };
+  if let Some(lib) = &cli.onnx_runtime {
+    ort::init_from(lib)
+      .commit()
+      .map_err(|e| format!("Failed to load ONNX Runtime from {}: {}", lib, e))?;
+  }
+  let session = Session::builder()
+    .map_err(|e| e.to_string())?
+    .commit_from_file(model)
+    .map_err(|e| format!("Failed to load model: {e}"))?;
+  Ok(Some(session))
+}
+
+#[tokio::main]
+async fn main() {
+  let cli = Cli::parse();
+  if !cli.stdio {
+    eprintln!("chatless-mcp: no transport selected, run with --stdio");
+    std::process::exit(2);
+  }
+
+  let session = match load_session(&cli) {
+    Ok(session) => session,
+    Err(e) => {
+      eprintln!("chatless-mcp: {}", e);
+      std::process::exit(1);
+    }
+  };
+  let onnx = Arc::new(OnnxState {
+    session: Mutex::new(session),
+    attention_mask: Default::default(),
+    token_type_ids: Default::default(),
+  });
+  let options = LocalServerOptions {
+    search_provider: Some(cli.search_provider),
+    search_api_key: cli
+      .search_api_key
+      .or_else(|| std::env::var("CHATLESS_SEARCH_API_KEY").ok()),
+    search_cse_id: cli.search_cse_id,
+    tokenizer_path: cli.tokenizer,
+    max_length: Some(cli.max_length),
+    document_dirs: cli.allow_dirs,
+    ..Default::default()
+  };
+
+  let service = match LocalServer::headless(options, onnx).serve(stdio()).await {
+    Ok(service) => service,
+    Err(e) => {
+      eprintln!("chatless-mcp: failed to start: {}", e);
+      std::process::exit(1);
+    }
+  };
+  if let Err(e) = service.waiting().await {
+    eprintln!("chatless-mcp: {}", e);
+    std::process::exit(1);
+  }
+}
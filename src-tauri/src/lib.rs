@@ -2,6 +2,7 @@
 use anyhow::Result;
 use crc32fast;
 use log::LevelFilter;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::path::BaseDirectory;
 use tauri::Manager;
@@ -17,13 +18,25 @@ pub mod mcp;
 #[path = "web_search/mod.rs"]
 pub mod web_search;
 
+/// 退出清理只执行一次（exit 命令、ExitRequested 与 Exit 事件都可能触发）
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// 先停止 SSE 流与内置服务器，再关闭 MCP 连接并清理 stdio 子进程，避免遗留孤儿进程
+async fn shutdown_services(app: &tauri::AppHandle) {
+  app.state::<sse::AppState>().shutdown();
+  mcp::shutdown::shutdown_all(&app.state::<mcp::state::McpState>()).await;
+}
+
 #[tauri::command]
-fn exit(app: tauri::AppHandle, code: i32) {
+async fn exit(app: tauri::AppHandle, code: i32) {
   #[cfg(not(any(target_os = "android", target_os = "ios")))]
   {
     use tauri_plugin_window_state::{AppHandleExt, StateFlags};
     let _ = app.save_window_state(StateFlags::all());
   }
+  if !SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+    shutdown_services(&app).await;
+  }
   std::process::exit(code);
 }
 
@@ -209,10 +222,41 @@ pub fn run() {
       // —— MCP Commands ——
       mcp::commands::mcp_connect,
       mcp::commands::mcp_disconnect,
+      mcp::commands::mcp_get_server_statuses,
+      mcp::commands::mcp_get_server_info,
       mcp::commands::mcp_list_tools,
       mcp::commands::mcp_call_tool,
+      mcp::commands::mcp_cancel_call,
+      mcp::commands::mcp_read_result_text,
+      mcp::commands::mcp_list_all_tools,
+      mcp::commands::mcp_call_qualified_tool,
+      mcp::commands::mcp_get_tool_filters,
+      mcp::commands::mcp_set_tool_filter,
+      mcp::commands::mcp_get_tool_policies,
+      mcp::commands::mcp_set_tool_policy,
+      mcp::commands::mcp_query_audit_log,
+      mcp::commands::mcp_respond_sampling,
+      mcp::commands::mcp_respond_elicitation,
+      mcp::commands::mcp_respond_kb_query,
+      mcp::commands::mcp_get_server_logs,
+      mcp::commands::mcp_diagnose,
+      mcp::commands::mcp_set_secret,
+      mcp::commands::mcp_delete_secret,
+      mcp::commands::mcp_list_secrets,
+      mcp::commands::mcp_import_preview,
+      mcp::commands::mcp_get_policy,
+      mcp::commands::mcp_save_policy,
+      mcp::commands::mcp_check_policy,
+      mcp::commands::mcp_authorize,
+      mcp::commands::mcp_get_auth_status,
+      mcp::commands::mcp_clear_auth,
+      mcp::commands::mcp_get_roots,
+      mcp::commands::mcp_set_roots,
       mcp::commands::mcp_list_resources,
       mcp::commands::mcp_read_resource,
+      mcp::commands::mcp_list_resource_templates,
+      mcp::commands::mcp_subscribe_resource,
+      mcp::commands::mcp_unsubscribe_resource,
       mcp::commands::mcp_list_prompts,
       mcp::commands::mcp_get_prompt,
       // Document parser commands
@@ -230,6 +274,7 @@ pub fn run() {
       sse::stop_sse,
       sse::start_local_sse_server,
       sse::start_local_mcp_sse,
+      sse::stop_local_mcp_sse,
       // —— HTTP Client Commands ——
       http_client::get_http_client_info,
       http_client::test_http_client,
@@ -241,8 +286,28 @@ pub fn run() {
       web_search::commands::native_web_fetch,
       web_search::commands::duckrush_search_api
     ])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|app, event| match event {
+      // 关闭窗口、Cmd+Q、托盘退出等都会经过这里：先拦下退出，清理完成后再以原退出码退出
+      tauri::RunEvent::ExitRequested { api, code, .. } => {
+        if !SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+          api.prevent_exit();
+          let app = app.clone();
+          tauri::async_runtime::spawn(async move {
+            shutdown_services(&app).await;
+            app.exit(code.unwrap_or(0));
+          });
+        }
+      }
+      // 未经过 ExitRequested 的退出路径（如系统注销），在此同步完成清理
+      tauri::RunEvent::Exit => {
+        if !SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+          tauri::async_runtime::block_on(shutdown_services(app));
+        }
+      }
+      _ => {}
+    });
 }
 
 /// 动态设置日志级别（支持在运行时从前端调整）
@@ -32,6 +32,8 @@ pub struct HttpClientConfig {
   pub disable_http2: bool,
   /// 代理URL
   pub proxy_url: Option<String>,
+  /// 额外的默认请求头（同名时覆盖浏览器模拟头）
+  pub extra_headers: Vec<(String, String)>,
 }
 
 impl Default for HttpClientConfig {
@@ -48,6 +50,7 @@ impl Default for HttpClientConfig {
       brotli: true,
       disable_http2: false,
       proxy_url: None,
+      extra_headers: Vec::new(),
     }
   }
 }
@@ -139,8 +142,22 @@ impl HttpClientManager {
     }
 
     // 浏览器模拟头
-    if config.browser_like_headers {
-      let headers = Self::build_browser_headers(config.user_agent.as_deref());
+    let mut headers = if config.browser_like_headers {
+      Self::build_browser_headers(config.user_agent.as_deref())
+    } else {
+      HeaderMap::new()
+    };
+
+    // 额外请求头（如鉴权头）；无法解析的头名/值直接跳过
+    for (name, value) in &config.extra_headers {
+      if let (Ok(name), Ok(value)) = (
+        HeaderName::from_bytes(name.as_bytes()),
+        HeaderValue::from_str(value),
+      ) {
+        headers.insert(name, value);
+      }
+    }
+    if !headers.is_empty() {
       builder = builder.default_headers(headers);
     }
 
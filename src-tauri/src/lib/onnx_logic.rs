@@ -96,88 +96,90 @@ pub async fn generate_embedding(
   app: AppHandle,
 ) -> Result<Vec<Vec<f32>>, String> {
   // 将计算密集型任务移动到阻塞线程池
-  tauri::async_runtime::spawn_blocking(move || {
-    let state = app.state::<OnnxState>();
-    let mut session_guard = state.session.lock().map_err(|e| e.to_string())?;
-    let session = session_guard
-      .as_mut()
-      .ok_or("ONNX Session not initialized")?;
-
-    // Convert to ndarray
-    let batch = input.input_ids.len();
-    if batch == 0 {
-      return Ok(vec![]);
-    }
-    let seq_len = input.input_ids[0].len();
-
-    let ids_flat: Vec<i64> = input.input_ids.into_iter().flatten().collect();
-    let mask_flat: Vec<i64> = input.attention_mask.into_iter().flatten().collect();
-    let token_type_flat: Vec<i64> = input.token_type_ids.into_iter().flatten().collect();
-
-    let mask_array =
-      Array2::from_shape_vec((batch, seq_len), mask_flat.clone()).map_err(|e| e.to_string())?;
-
-    // Convert ndarray to Tensor
-    let ids_tensor =
-      Tensor::<i64>::from_array(([batch, seq_len], ids_flat)).map_err(|e| e.to_string())?;
-    let mask_tensor =
-      Tensor::<i64>::from_array(([batch, seq_len], mask_flat)).map_err(|e| e.to_string())?;
-    let token_type_tensor =
-      Tensor::<i64>::from_array(([batch, seq_len], token_type_flat)).map_err(|e| e.to_string())?;
-
-    // Run inference
-    let outputs = session
-      .run(ort::inputs![
-          "input_ids" => &ids_tensor,
-          "attention_mask" => &mask_tensor,
-          "token_type_ids" => &token_type_tensor
-      ])
-      .map_err(|e| e.to_string())?;
-
-    // Extract token_embeddings
-    let hidden_state = outputs["token_embeddings"]
-      .try_extract_array::<f32>()
-      .map_err(|e| e.to_string())?;
-
-    let shape = hidden_state.shape();
-    if shape.len() != 3 {
-      return Err(format!("Unsupported output dimensions: {:?}", shape));
-    }
-    let hidden_size = shape[2];
-
-    // Mean pooling
-    let mut results: Vec<Vec<f32>> = Vec::with_capacity(batch);
-    let data = hidden_state.as_slice().ok_or("Cannot get output slice")?;
-    let mut idx = 0;
-
-    for b in 0..batch {
-      let mut pooled = vec![0.0f32; hidden_size];
-      let mut valid_tokens = 0f32;
-      for t in 0..seq_len {
-        let mask_val = mask_array[(b, t)];
-        for h in 0..hidden_size {
-          let val = data[idx];
-          idx += 1;
-          if mask_val == 1 {
-            pooled[h] += val;
-          }
-        }
+  tauri::async_runtime::spawn_blocking(move || run_embedding(&app.state::<OnnxState>(), input))
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 在已加载的会话上执行推理并做 mean pooling（阻塞调用，供命令与无界面模式共用）
+pub fn run_embedding(state: &OnnxState, input: EmbeddingInput) -> Result<Vec<Vec<f32>>, String> {
+  let mut session_guard = state.session.lock().map_err(|e| e.to_string())?;
+  let session = session_guard
+    .as_mut()
+    .ok_or("ONNX Session not initialized")?;
+
+  // Convert to ndarray
+  let batch = input.input_ids.len();
+  if batch == 0 {
+    return Ok(vec![]);
+  }
+  let seq_len = input.input_ids[0].len();
+
+  let ids_flat: Vec<i64> = input.input_ids.into_iter().flatten().collect();
+  let mask_flat: Vec<i64> = input.attention_mask.into_iter().flatten().collect();
+  let token_type_flat: Vec<i64> = input.token_type_ids.into_iter().flatten().collect();
+
+  let mask_array =
+    Array2::from_shape_vec((batch, seq_len), mask_flat.clone()).map_err(|e| e.to_string())?;
+
+  // Convert ndarray to Tensor
+  let ids_tensor =
+    Tensor::<i64>::from_array(([batch, seq_len], ids_flat)).map_err(|e| e.to_string())?;
+  let mask_tensor =
+    Tensor::<i64>::from_array(([batch, seq_len], mask_flat)).map_err(|e| e.to_string())?;
+  let token_type_tensor =
+    Tensor::<i64>::from_array(([batch, seq_len], token_type_flat)).map_err(|e| e.to_string())?;
+
+  // Run inference
+  let outputs = session
+    .run(ort::inputs![
+        "input_ids" => &ids_tensor,
+        "attention_mask" => &mask_tensor,
+        "token_type_ids" => &token_type_tensor
+    ])
+    .map_err(|e| e.to_string())?;
+
+  // Extract token_embeddings
+  let hidden_state = outputs["token_embeddings"]
+    .try_extract_array::<f32>()
+    .map_err(|e| e.to_string())?;
+
+  let shape = hidden_state.shape();
+  if shape.len() != 3 {
+    return Err(format!("Unsupported output dimensions: {:?}", shape));
+  }
+  let hidden_size = shape[2];
+
+  // Mean pooling
+  let mut results: Vec<Vec<f32>> = Vec::with_capacity(batch);
+  let data = hidden_state.as_slice().ok_or("Cannot get output slice")?;
+  let mut idx = 0;
+
+  for b in 0..batch {
+    let mut pooled = vec![0.0f32; hidden_size];
+    let mut valid_tokens = 0f32;
+    for t in 0..seq_len {
+      let mask_val = mask_array[(b, t)];
+      for h in 0..hidden_size {
+        let val = data[idx];
+        idx += 1;
         if mask_val == 1 {
-          valid_tokens += 1.0;
+          pooled[h] += val;
         }
       }
-      if valid_tokens > 0.0 {
-        for v in pooled.iter_mut() {
-          *v /= valid_tokens;
-        }
+      if mask_val == 1 {
+        valid_tokens += 1.0;
       }
-      results.push(pooled);
     }
+    if valid_tokens > 0.0 {
+      for v in pooled.iter_mut() {
+        *v /= valid_tokens;
+      }
+    }
+    results.push(pooled);
+  }
 
-    Ok(results)
-  })
-  .await
-  .map_err(|e| e.to_string())?
+  Ok(results)
 }
 
 #[tauri::command]
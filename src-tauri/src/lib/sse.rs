@@ -1,12 +1,8 @@
 // src-tauri/src/lib/sse.rs
+use crate::mcp::local_server::{self, LocalServerHandle, LocalServerInfo, LocalServerOptions};
 use futures_util::StreamExt;
 use lazy_static::lazy_static;
 use reqwest::Method;
-use rmcp::{
-  model::{ServerCapabilities, ServerInfo},
-  transport::sse_server::SseServer,
-  ServerHandler,
-};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Mutex;
@@ -24,12 +20,37 @@ lazy_static! {
 /// 全局状态，用于在 `start_sse` 和 `stop_sse` 之间共享关闭信号
 pub struct AppState {
   pub sse_shutdown_sender: Mutex<Option<broadcast::Sender<()>>>,
+  /// 内置 MCP 服务器（运行中时为 Some）
+  pub local_mcp_server: Mutex<Option<LocalServerHandle>>,
 }
 
 impl AppState {
   pub fn new() -> Self {
     Self {
       sse_shutdown_sender: Mutex::new(None),
+      local_mcp_server: Mutex::new(None),
+    }
+  }
+
+  /// 应用退出前停止进行中的 SSE 流、内置 MCP 服务器与本地测试服务子进程
+  pub fn shutdown(&self) {
+    let sender = self
+      .sse_shutdown_sender
+      .lock()
+      .ok()
+      .and_then(|mut g| g.take());
+    if let Some(sender) = sender {
+      let _ = sender.send(());
+    }
+    let server = self.local_mcp_server.lock().ok().and_then(|mut g| g.take());
+    if let Some(server) = server {
+      server.cancel();
+      log::info!("[MCP/local] Built-in MCP server stopped");
+    }
+    let child = SERVER_CHILD.lock().ok().and_then(|mut g| g.take());
+    if let Some(mut child) = child {
+      let _ = child.kill();
+      let _ = child.wait();
     }
   }
 }
@@ -254,27 +275,42 @@ pub async fn start_local_sse_server(address: String) -> Result<(), String> {
   Ok(())
 }
 
-/// 启动一个“最小 MCP SSE 服务器”（工具为空，仅用于握手验证）
+/// 启动内置 MCP SSE 服务器，向本机的外部 Agent（如 IDE）暴露 Chatless 的工具。
+/// - address: 仅允许回环地址，例如 "127.0.0.1:8787"
+/// - options: 搜索提供商与嵌入模型配置
+/// 客户端需携带 `Authorization: Bearer <token>`；已在运行时先停止旧实例并等待其释放端口。
 #[tauri::command]
-pub async fn start_local_mcp_sse(address: String) -> Result<(), String> {
-  // 使用 rmcp 的 SseServer（真实 MCP SSE 管道），提供一个空 ServerHandler
-  struct Dummy;
-  impl ServerHandler for Dummy {
-    fn get_info(&self) -> ServerInfo {
-      ServerInfo {
-        capabilities: ServerCapabilities::builder().enable_tools().build(),
-        instructions: Some("Dummy MCP SSE server".into()),
-        ..Default::default()
-      }
-    }
+pub async fn start_local_mcp_sse(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  address: String,
+  options: Option<LocalServerOptions>,
+) -> Result<LocalServerInfo, String> {
+  let previous = state.local_mcp_server.lock().ok().and_then(|mut g| g.take());
+  if let Some(previous) = previous {
+    previous.stop().await;
+  }
+  let (server, info) = local_server::start(app, &address, options.unwrap_or_default()).await?;
+  if let Ok(mut guard) = state.local_mcp_server.lock() {
+    *guard = Some(server);
   }
+  Ok(info)
+}
 
-  let bind: std::net::SocketAddr = address
-    .parse()
-    .map_err(|e| format!("invalid address {}: {}", address, e))?;
-  let sse = SseServer::serve(bind)
-    .await
-    .map_err(|e| format!("start sse failed: {}", e))?;
-  let _ct = sse.with_service::<Dummy, _>(|| Dummy);
-  Ok(())
+/// 停止内置 MCP SSE 服务器
+#[tauri::command]
+pub async fn stop_local_mcp_sse(state: State<'_, AppState>) -> Result<(), String> {
+  let server = state
+    .local_mcp_server
+    .lock()
+    .map_err(|e| format!("Failed to acquire lock: {}", e))?
+    .take();
+  match server {
+    Some(server) => {
+      server.stop().await;
+      log::info!("[MCP/local] Built-in MCP server stopped");
+      Ok(())
+    }
+    None => Err("Built-in MCP server is not running.".into()),
+  }
 }
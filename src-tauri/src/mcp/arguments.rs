@@ -0,0 +1,93 @@
+use crate::mcp::cache;
+use crate::mcp::schema::{self, SchemaError};
+use crate::mcp::state::McpState;
+use rmcp::model::Tool;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// 参数校验失败时工具调用返回的错误前缀，后接 JSON 格式的 [`McpArgumentError`]
+pub const INVALID_ARGUMENTS: &str = "MCP_INVALID_ARGUMENTS";
+
+/// 结构化的参数错误，`message` 可直接作为工具结果回传给模型让其修正后重试
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpArgumentError {
+  pub server_name: String,
+  pub tool_name: String,
+  pub errors: Vec<SchemaError>,
+  pub message: String,
+  pub input_schema: Value,
+}
+
+/// 按工具的 inputSchema 修正并校验参数
+pub(crate) fn prepare(
+  tool: &Tool,
+  args: Option<Map<String, Value>>,
+) -> Result<Option<Map<String, Value>>, Vec<SchemaError>> {
+  let input_schema = Value::Object((*tool.input_schema).clone());
+  let mut value = Value::Object(args.unwrap_or_default());
+  let applied = schema::coerce(&input_schema, &mut value);
+  if !applied.is_empty() {
+    log::debug!(
+      "[MCP/args] Coerced arguments for tool {}: {}",
+      tool.name,
+      applied.join("; ")
+    );
+  }
+  let errors = schema::validate(&input_schema, &value);
+  if !errors.is_empty() {
+    return Err(errors);
+  }
+  match value {
+    Value::Object(map) => Ok(Some(map)),
+    _ => Ok(None),
+  }
+}
+
+/// 发送前检查参数。工具列表不可用或工具不在缓存中时原样放行，由服务器自行处理。
+pub(crate) async fn check(
+  state: &McpState,
+  server_name: &str,
+  tool_name: &str,
+  args: Option<Map<String, Value>>,
+) -> Result<Option<Map<String, Value>>, String> {
+  let tools = match cache::tools(state, server_name, false).await {
+    Ok(tools) => tools,
+    Err(e) => {
+      log::debug!(
+        "[MCP/args] Tool list unavailable for {}, skipping validation: {}",
+        server_name,
+        e
+      );
+      return Ok(args);
+    }
+  };
+  let Some(tool) = tools.iter().find(|t| t.name == tool_name) else {
+    log::debug!(
+      "[MCP/args] Tool {} not in cached list of {}, skipping validation",
+      tool_name,
+      server_name
+    );
+    return Ok(args);
+  };
+
+  prepare(tool, args).map_err(|errors| {
+    let error = McpArgumentError {
+      server_name: server_name.to_string(),
+      tool_name: tool_name.to_string(),
+      message: format!(
+        "Invalid arguments for tool `{}`: {}. Fix the arguments to match the tool's input schema and call it again.",
+        tool_name,
+        schema::describe(&errors)
+      ),
+      errors,
+      input_schema: Value::Object((*tool.input_schema).clone()),
+    };
+    log::warn!("[MCP/args] {}", error.message);
+    format!(
+      "{}: {}",
+      INVALID_ARGUMENTS,
+      serde_json::to_string(&error).unwrap_or_default()
+    )
+  })
+}
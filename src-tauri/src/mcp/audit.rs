@@ -0,0 +1,351 @@
+use crate::mcp::state::McpState;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// 审计数据库文件（位于应用数据目录）
+const AUDIT_DB: &str = "mcp-audit.db";
+/// 审计记录保留天数，打开数据库时清理更早的记录
+const RETENTION_DAYS: i64 = 90;
+/// 脱敏后参数中单个字符串的最大长度
+const MAX_ARG_CHARS: usize = 200;
+/// 参数名（小写并去掉 `_`、`-` 后）以这些词结尾时，值会被替换为 `[REDACTED]`
+const SENSITIVE_SUFFIXES: [&str; 12] = [
+  "password",
+  "passwd",
+  "secret",
+  "secrets",
+  "token",
+  "apikey",
+  "accesskey",
+  "privatekey",
+  "authorization",
+  "credential",
+  "credentials",
+  "cookie",
+];
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS mcp_tool_calls (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  call_id TEXT NOT NULL,
+  server_name TEXT NOT NULL,
+  tool_name TEXT NOT NULL,
+  args_hash TEXT NOT NULL,
+  args_redacted TEXT NOT NULL,
+  decision TEXT NOT NULL,
+  started_at INTEGER NOT NULL,
+  duration_ms INTEGER NOT NULL,
+  result_bytes INTEGER,
+  is_error INTEGER NOT NULL DEFAULT 0,
+  error TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_mcp_tool_calls_started ON mcp_tool_calls (started_at);
+CREATE INDEX IF NOT EXISTS idx_mcp_tool_calls_tool ON mcp_tool_calls (server_name, tool_name);
+"#;
+
+/// 一条审计记录
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpAuditEntry {
+  pub id: i64,
+  pub call_id: String,
+  pub server_name: String,
+  pub tool_name: String,
+  /// 规范化参数（键排序后的 JSON）的 SHA-256，可用于比对相同调用而不暴露原值
+  pub args_hash: String,
+  pub args_redacted: Value,
+  /// allowed | approved | denied
+  pub decision: String,
+  /// Unix 毫秒时间戳
+  pub started_at: i64,
+  pub duration_ms: i64,
+  pub result_bytes: Option<i64>,
+  pub is_error: bool,
+  pub error: Option<String>,
+}
+
+/// 审计日志查询条件（均可选）
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpAuditQuery {
+  pub server_name: Option<String>,
+  pub tool_name: Option<String>,
+  /// 起止时间（Unix 毫秒，左闭右开）
+  pub since: Option<i64>,
+  pub until: Option<i64>,
+  pub errors_only: bool,
+  pub limit: Option<i64>,
+  pub offset: Option<i64>,
+}
+
+/// 进行中的一次调用，结束时写入审计日志
+pub(crate) struct CallRecord {
+  call_id: String,
+  server_name: String,
+  tool_name: String,
+  args_hash: String,
+  args_redacted: String,
+  started_at: i64,
+  started: Instant,
+}
+
+fn now_ms() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as i64)
+    .unwrap_or(0)
+}
+
+/// 键排序后的紧凑 JSON，保证相同参数得到相同哈希
+fn canonical(value: &Value) -> String {
+  match value {
+    Value::Object(map) => {
+      let mut keys: Vec<&String> = map.keys().collect();
+      keys.sort();
+      let fields: Vec<String> = keys
+        .into_iter()
+        .map(|k| format!("{}:{}", Value::String(k.clone()), canonical(&map[k])))
+        .collect();
+      format!("{{{}}}", fields.join(","))
+    }
+    Value::Array(items) => format!(
+      "[{}]",
+      items.iter().map(canonical).collect::<Vec<_>>().join(",")
+    ),
+    other => other.to_string(),
+  }
+}
+
+fn is_sensitive(key: &str) -> bool {
+  let key: String = key
+    .chars()
+    .filter(|c| *c != '_' && *c != '-')
+    .collect::<String>()
+    .to_ascii_lowercase();
+  SENSITIVE_SUFFIXES.iter().any(|s| key.ends_with(s))
+}
+
+/// 脱敏：敏感键的值替换为 `[REDACTED]`，过长字符串截断
+fn redact(value: &Value) -> Value {
+  match value {
+    Value::Object(map) => Value::Object(
+      map
+        .iter()
+        .map(|(k, v)| {
+          let v = if is_sensitive(k) {
+            Value::String("[REDACTED]".into())
+          } else {
+            redact(v)
+          };
+          (k.clone(), v)
+        })
+        .collect(),
+    ),
+    Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+    Value::String(s) if s.chars().count() > MAX_ARG_CHARS => {
+      let mut cut: String = s.chars().take(MAX_ARG_CHARS).collect();
+      cut.push('…');
+      Value::String(cut)
+    }
+    other => other.clone(),
+  }
+}
+
+impl CallRecord {
+  pub(crate) fn start(
+    call_id: &str,
+    server_name: &str,
+    tool_name: &str,
+    args: Option<&Map<String, Value>>,
+  ) -> Self {
+    let args = Value::Object(args.cloned().unwrap_or_default());
+    let args_hash: String = Sha256::digest(canonical(&args).as_bytes())
+      .iter()
+      .map(|b| format!("{:02x}", b))
+      .collect();
+    Self {
+      call_id: call_id.to_string(),
+      server_name: server_name.to_string(),
+      tool_name: tool_name.to_string(),
+      args_hash,
+      args_redacted: redact(&args).to_string(),
+      started_at: now_ms(),
+      started: Instant::now(),
+    }
+  }
+}
+
+async fn pool<'a>(app: &AppHandle, state: &'a McpState) -> Result<&'a SqlitePool, String> {
+  state
+    .audit_db
+    .get_or_try_init(|| async {
+      let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+      std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+      let options = SqliteConnectOptions::new()
+        .filename(dir.join(AUDIT_DB))
+        .create_if_missing(true);
+      let pool = SqlitePoolOptions::new()
+        .max_connections(2)
+        .connect_with(options)
+        .await
+        .map_err(|e| format!("Failed to open audit database: {}", e))?;
+      sqlx::raw_sql(SCHEMA)
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to initialize audit database: {}", e))?;
+      let cutoff = now_ms() - RETENTION_DAYS * 24 * 3600 * 1000;
+      if let Err(e) = sqlx::query("DELETE FROM mcp_tool_calls WHERE started_at < ?")
+        .bind(cutoff)
+        .execute(&pool)
+        .await
+      {
+        log::warn!("[MCP/audit] Failed to prune old records: {}", e);
+      }
+      Ok(pool)
+    })
+    .await
+}
+
+/// 写入一条审计记录。审计失败只记日志，不影响工具调用本身。
+/// `outcome` 为最终返回给前端的结果（被拒绝时 decision 为 "denied"）
+pub(crate) async fn record(
+  app: &AppHandle,
+  state: &McpState,
+  call: CallRecord,
+  decision: &str,
+  outcome: &Result<Value, String>,
+) {
+  let (result_bytes, is_error, error) = match outcome {
+    Ok(value) => {
+      let is_error = value
+        .get("isError")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+      let bytes = serde_json::to_string(value).map(|s| s.len() as i64).ok();
+      (bytes, is_error, None)
+    }
+    Err(e) => (None, true, Some(e.clone())),
+  };
+  let pool = match pool(app, state).await {
+    Ok(p) => p,
+    Err(e) => {
+      log::warn!("[MCP/audit] {}", e);
+      return;
+    }
+  };
+  let res = sqlx::query(
+    "INSERT INTO mcp_tool_calls (call_id, server_name, tool_name, args_hash, args_redacted, \
+     decision, started_at, duration_ms, result_bytes, is_error, error) \
+     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+  )
+  .bind(&call.call_id)
+  .bind(&call.server_name)
+  .bind(&call.tool_name)
+  .bind(&call.args_hash)
+  .bind(&call.args_redacted)
+  .bind(decision)
+  .bind(call.started_at)
+  .bind(call.started.elapsed().as_millis() as i64)
+  .bind(result_bytes)
+  .bind(is_error)
+  .bind(error)
+  .execute(pool)
+  .await;
+  if let Err(e) = res {
+    log::warn!("[MCP/audit] Failed to record call {}: {}", call.call_id, e);
+  }
+}
+
+/// 按条件查询审计日志，最新的在前（默认最多 100 条）
+pub(crate) async fn query(
+  app: &AppHandle,
+  state: &McpState,
+  q: McpAuditQuery,
+) -> Result<Vec<McpAuditEntry>, String> {
+  let pool = pool(app, state).await?;
+  let rows = sqlx::query(
+    "SELECT id, call_id, server_name, tool_name, args_hash, args_redacted, decision, \
+     started_at, duration_ms, result_bytes, is_error, error FROM mcp_tool_calls \
+     WHERE (?1 IS NULL OR server_name = ?1) AND (?2 IS NULL OR tool_name = ?2) \
+     AND (?3 IS NULL OR started_at >= ?3) AND (?4 IS NULL OR started_at < ?4) \
+     AND (?5 = 0 OR is_error = 1) \
+     ORDER BY id DESC LIMIT ?6 OFFSET ?7",
+  )
+  .bind(q.server_name)
+  .bind(q.tool_name)
+  .bind(q.since)
+  .bind(q.until)
+  .bind(q.errors_only)
+  .bind(q.limit.unwrap_or(100))
+  .bind(q.offset.unwrap_or(0))
+  .fetch_all(pool)
+  .await
+  .map_err(|e| format!("Failed to query audit log: {}", e))?;
+
+  rows
+    .iter()
+    .map(|row| {
+      let args: String = row.try_get("args_redacted")?;
+      Ok(McpAuditEntry {
+        id: row.try_get("id")?,
+        call_id: row.try_get("call_id")?,
+        server_name: row.try_get("server_name")?,
+        tool_name: row.try_get("tool_name")?,
+        args_hash: row.try_get("args_hash")?,
+        args_redacted: serde_json::from_str(&args).unwrap_or(Value::String(args)),
+        decision: row.try_get("decision")?,
+        started_at: row.try_get("started_at")?,
+        duration_ms: row.try_get("duration_ms")?,
+        result_bytes: row.try_get("result_bytes")?,
+        is_error: row.try_get("is_error")?,
+        error: row.try_get("error")?,
+      })
+    })
+    .collect::<Result<Vec<_>, sqlx::Error>>()
+    .map_err(|e| format!("Failed to read audit log: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_hash_is_order_independent_and_args_are_redacted() {
+    let a = json!({ "b": 1, "a": { "y": [1, 2], "x": "v" } });
+    let b = json!({ "a": { "x": "v", "y": [1, 2] }, "b": 1 });
+    assert_eq!(canonical(&a), canonical(&b));
+
+    let r = CallRecord::start(
+      "c1",
+      "s",
+      "t",
+      json!({
+        "query": "x".repeat(300),
+        "max_tokens": 10,
+        "apiKey": "sk-123",
+        "nested": { "Authorization": "Bearer z", "access_token": "t" }
+      })
+      .as_object(),
+    );
+    let redacted: Value = serde_json::from_str(&r.args_redacted).unwrap();
+    assert_eq!(redacted["apiKey"], json!("[REDACTED]"));
+    assert_eq!(redacted["nested"]["Authorization"], json!("[REDACTED]"));
+    assert_eq!(redacted["nested"]["access_token"], json!("[REDACTED]"));
+    assert_eq!(redacted["max_tokens"], json!(10));
+    assert_eq!(
+      redacted["query"].as_str().unwrap().chars().count(),
+      MAX_ARG_CHARS + 1
+    );
+    assert!(!r.args_redacted.contains("sk-123"));
+  }
+}
@@ -0,0 +1,636 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_store::StoreExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{timeout, Duration};
+
+/// 各服务器 OAuth 凭据的持久化文件（key 为服务器名）
+const AUTH_STORE: &str = "mcp-auth.json";
+/// 等待浏览器回调的最长时间
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+/// 单个回调连接读取请求行的上限，避免空闲连接阻塞后续回调
+const CALLBACK_READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// 回调请求行的最大长度
+const MAX_REQUEST_LINE: usize = 8192;
+/// 探测 401 响应（读取 WWW-Authenticate）的超时
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// 距过期不足该秒数即视为需要刷新
+const REFRESH_MARGIN_SECS: u64 = 60;
+const CALLBACK_PATH: &str = "/callback";
+
+/// 持久化的授权信息：动态注册得到的客户端 + 当前令牌
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredAuth {
+  /// 令牌对应的 MCP 服务器地址；baseUrl 变更后旧令牌不再使用
+  pub resource: String,
+  pub client_id: String,
+  pub client_secret: Option<String>,
+  pub redirect_uri: String,
+  pub token_endpoint: String,
+  pub access_token: String,
+  pub refresh_token: Option<String>,
+  /// 过期时间（Unix 秒）；服务器未返回 expires_in 时为空
+  pub expires_at: Option<u64>,
+  pub scope: Option<String>,
+}
+
+/// 返回给前端的授权状态（不含令牌本身）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpAuthStatus {
+  pub server_name: String,
+  pub authorized: bool,
+  pub expires_at: Option<u64>,
+  pub scope: Option<String>,
+}
+
+impl McpAuthStatus {
+  pub fn of(server_name: &str, auth: Option<&StoredAuth>) -> Self {
+    Self {
+      server_name: server_name.to_string(),
+      authorized: auth.is_some(),
+      expires_at: auth.and_then(|a| a.expires_at),
+      scope: auth.and_then(|a| a.scope.clone()),
+    }
+  }
+}
+
+/// RFC 8414 授权服务器元数据（只取用到的字段）
+#[derive(Debug, Clone, Deserialize)]
+struct AuthServerMetadata {
+  authorization_endpoint: String,
+  token_endpoint: String,
+  registration_endpoint: Option<String>,
+  scopes_supported: Option<Vec<String>>,
+}
+
+/// RFC 9728 受保护资源元数据
+#[derive(Debug, Deserialize)]
+struct ProtectedResourceMetadata {
+  #[serde(default)]
+  authorization_servers: Vec<String>,
+  scopes_supported: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistrationResponse {
+  client_id: String,
+  client_secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+  access_token: String,
+  refresh_token: Option<String>,
+  expires_in: Option<u64>,
+  scope: Option<String>,
+}
+
+fn now_secs() -> u64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
+}
+
+pub(crate) fn load_auth(app: &AppHandle, server_name: &str) -> Option<StoredAuth> {
+  let store = match app.store(AUTH_STORE) {
+    Ok(s) => s,
+    Err(e) => {
+      log::warn!("[MCP/auth] Failed to open auth store: {}", e);
+      return None;
+    }
+  };
+  store
+    .get(server_name)
+    .and_then(|v| serde_json::from_value::<StoredAuth>(v).ok())
+}
+
+fn save_auth(app: &AppHandle, server_name: &str, auth: &StoredAuth) -> Result<(), String> {
+  let store = app
+    .store(AUTH_STORE)
+    .map_err(|e| format!("Failed to open auth store: {}", e))?;
+  let value = serde_json::to_value(auth).map_err(|e| e.to_string())?;
+  store.set(server_name, value);
+  store
+    .save()
+    .map_err(|e| format!("Failed to save auth store: {}", e))
+}
+
+pub(crate) fn clear_auth(app: &AppHandle, server_name: &str) -> Result<(), String> {
+  let store = app
+    .store(AUTH_STORE)
+    .map_err(|e| format!("Failed to open auth store: {}", e))?;
+  store.delete(server_name);
+  store
+    .save()
+    .map_err(|e| format!("Failed to save auth store: {}", e))
+}
+
+fn random_token() -> String {
+  URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+/// PKCE S256：challenge = BASE64URL(SHA256(verifier))
+fn pkce_challenge(verifier: &str) -> String {
+  URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// 按 RFC 8414 / 9728 规则拼接 well-known 地址：路径部分追加在 well-known 段之后
+fn well_known(url: &Url, suffix: &str) -> String {
+  let path = url.path().trim_end_matches('/');
+  format!("{}/.well-known/{}{}", origin(url), suffix, path)
+}
+
+fn origin(url: &Url) -> String {
+  url.origin().ascii_serialization()
+}
+
+async fn get_json<T: for<'de> Deserialize<'de>>(client: &Client, url: &str) -> Option<T> {
+  let resp = client
+    .get(url)
+    .header("Accept", "application/json")
+    .send()
+    .await
+    .ok()?;
+  if !resp.status().is_success() {
+    log::debug!("[MCP/auth] {} -> {}", url, resp.status());
+    return None;
+  }
+  resp.json::<T>().await.ok()
+}
+
+/// 从 `WWW-Authenticate` 头中取出 RFC 9728 的 `resource_metadata` 参数
+fn parse_resource_metadata(header: &str) -> Option<String> {
+  let lower = header.to_ascii_lowercase();
+  let key = "resource_metadata=";
+  let start = lower
+    .match_indices(key)
+    .map(|(i, _)| i)
+    // 排除 `xresource_metadata=` 之类的参数名
+    .find(|&i| i == 0 || matches!(lower.as_bytes()[i - 1], b' ' | b',' | b'\t'))?
+    + key.len();
+  let rest = &header[start..];
+  let value = match rest.strip_prefix('"') {
+    Some(quoted) => &quoted[..quoted.find('"')?],
+    None => rest.split([',', ' ']).next().unwrap_or_default(),
+  };
+  (!value.is_empty()).then(|| value.to_string())
+}
+
+/// 未携带令牌访问服务器，从 401 响应的 `WWW-Authenticate` 中取受保护资源元数据地址
+async fn probe_resource_metadata(client: &Client, base: &Url) -> Option<String> {
+  let resp = client
+    .get(base.as_str())
+    .header("Accept", "text/event-stream")
+    .timeout(PROBE_TIMEOUT)
+    .send()
+    .await
+    .ok()?;
+  if resp.status() != reqwest::StatusCode::UNAUTHORIZED {
+    return None;
+  }
+  resp
+    .headers()
+    .get_all(reqwest::header::WWW_AUTHENTICATE)
+    .iter()
+    .filter_map(|v| v.to_str().ok())
+    .find_map(parse_resource_metadata)
+}
+
+/// 发现授权服务器：先查受保护资源元数据（优先使用 401 响应中给出的地址，再试 well-known），
+/// 再查授权服务器元数据；都不可用时按 MCP 规范回退到服务器源站的默认端点。
+async fn discover(client: &Client, base: &Url) -> (AuthServerMetadata, Option<Vec<String>>) {
+  let mut issuer = Url::parse(&origin(base)).unwrap_or_else(|_| base.clone());
+  let mut scopes = None;
+
+  let advertised = probe_resource_metadata(client, base).await;
+  if let Some(url) = &advertised {
+    log::info!("[MCP/auth] Server advertised resource metadata at {}", url);
+  }
+  for candidate in advertised.into_iter().chain([
+    well_known(base, "oauth-protected-resource"),
+    format!("{}/.well-known/oauth-protected-resource", origin(base)),
+  ]) {
+    if let Some(meta) = get_json::<ProtectedResourceMetadata>(client, &candidate).await {
+      if let Some(url) = meta
+        .authorization_servers
+        .first()
+        .and_then(|s| Url::parse(s).ok())
+      {
+        issuer = url;
+      }
+      scopes = meta.scopes_supported;
+      break;
+    }
+  }
+  log::info!("[MCP/auth] Authorization server: {}", issuer);
+
+  for candidate in [
+    well_known(&issuer, "oauth-authorization-server"),
+    well_known(&issuer, "openid-configuration"),
+    format!(
+      "{}/.well-known/openid-configuration",
+      issuer.as_str().trim_end_matches('/')
+    ),
+  ] {
+    if let Some(meta) = get_json::<AuthServerMetadata>(client, &candidate).await {
+      let scopes = scopes.or_else(|| meta.scopes_supported.clone());
+      return (meta, scopes);
+    }
+  }
+
+  log::warn!("[MCP/auth] No authorization server metadata found, using default endpoints");
+  let root = origin(&issuer);
+  (
+    AuthServerMetadata {
+      authorization_endpoint: format!("{}/authorize", root),
+      token_endpoint: format!("{}/token", root),
+      registration_endpoint: Some(format!("{}/register", root)),
+      scopes_supported: None,
+    },
+    scopes,
+  )
+}
+
+/// RFC 7591 动态客户端注册（公共客户端，不使用 client secret 认证）
+async fn register_client(
+  client: &Client,
+  endpoint: &str,
+  redirect_uri: &str,
+) -> Result<RegistrationResponse, String> {
+  let body = serde_json::json!({
+    "client_name": "Chatless",
+    "redirect_uris": [redirect_uri],
+    "grant_types": ["authorization_code", "refresh_token"],
+    "response_types": ["code"],
+    "token_endpoint_auth_method": "none",
+  });
+  let resp = client
+    .post(endpoint)
+    .json(&body)
+    .send()
+    .await
+    .map_err(|e| format!("Client registration failed: {}", e))?;
+  let status = resp.status();
+  if !status.is_success() {
+    let text = resp.text().await.unwrap_or_default();
+    return Err(format!("Client registration failed ({}): {}", status, text));
+  }
+  resp
+    .json::<RegistrationResponse>()
+    .await
+    .map_err(|e| format!("Invalid registration response: {}", e))
+}
+
+async fn request_token(
+  client: &Client,
+  endpoint: &str,
+  form: &[(&str, &str)],
+) -> Result<TokenResponse, String> {
+  let resp = client
+    .post(endpoint)
+    .header("Accept", "application/json")
+    .form(form)
+    .send()
+    .await
+    .map_err(|e| format!("Token request failed: {}", e))?;
+  let status = resp.status();
+  if !status.is_success() {
+    let text = resp.text().await.unwrap_or_default();
+    return Err(format!("Token request failed ({}): {}", status, text));
+  }
+  resp
+    .json::<TokenResponse>()
+    .await
+    .map_err(|e| format!("Invalid token response: {}", e))
+}
+
+/// 从回调请求目标（如 `/callback?code=..&state=..`）中取出授权码。
+/// 非回调路径、无法解析或 state 不匹配的请求返回 Ok(None)，由调用方继续等待
+/// （浏览器可能先请求 favicon，本机其他程序也可能访问该端口）。
+fn parse_callback(target: &str, expected_state: &str) -> Result<Option<String>, String> {
+  let Ok(url) = Url::parse(&format!("http://127.0.0.1{}", target)) else {
+    return Ok(None);
+  };
+  if url.path() != CALLBACK_PATH {
+    return Ok(None);
+  }
+  let param = |key: &str| {
+    url
+      .query_pairs()
+      .find(|(k, _)| k == key)
+      .map(|(_, v)| v.to_string())
+  };
+  if param("state").as_deref() != Some(expected_state) {
+    log::warn!("[MCP/auth] Ignoring callback request with mismatched state");
+    return Ok(None);
+  }
+  if let Some(err) = param("error") {
+    let detail = param("error_description").unwrap_or_default();
+    return Err(
+      format!("Authorization denied: {} {}", err, detail)
+        .trim_end()
+        .to_string(),
+    );
+  }
+  param("code")
+    .map(Some)
+    .ok_or_else(|| "Authorization callback missing code".to_string())
+}
+
+/// 读取 HTTP 请求行（如 `GET /callback?.. HTTP/1.1`）
+async fn read_request_line(socket: &mut TcpStream) -> Option<String> {
+  let mut buf = Vec::new();
+  let mut chunk = [0u8; 1024];
+  while !buf.contains(&b'\n') && buf.len() < MAX_REQUEST_LINE {
+    let n = socket.read(&mut chunk).await.ok()?;
+    if n == 0 {
+      break;
+    }
+    buf.extend_from_slice(&chunk[..n]);
+  }
+  String::from_utf8_lossy(&buf)
+    .lines()
+    .next()
+    .map(str::to_string)
+}
+
+/// 在回环地址上等待浏览器重定向，返回授权码。
+/// 空闲、畸形或 state 不匹配的请求只会被忽略，直到收到本次授权的回调
+async fn wait_for_callback(listener: TcpListener, expected_state: &str) -> Result<String, String> {
+  loop {
+    let (mut socket, _) = listener.accept().await.map_err(|e| e.to_string())?;
+    let request_line = match timeout(CALLBACK_READ_TIMEOUT, read_request_line(&mut socket)).await {
+      Ok(Some(line)) => line,
+      Ok(None) | Err(_) => {
+        log::debug!("[MCP/auth] Dropping idle or unreadable callback connection");
+        continue;
+      }
+    };
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let result = parse_callback(target, expected_state);
+    let (status, message) = match &result {
+      Ok(None) => ("404 Not Found", "Not found"),
+      Ok(Some(_)) => (
+        "200 OK",
+        "Authorization complete. You can close this window and return to Chatless.",
+      ),
+      Err(_) => (
+        "400 Bad Request",
+        "Authorization failed. Please return to Chatless for details.",
+      ),
+    };
+    let body = format!(
+      "<!doctype html><html><head><meta charset=\"utf-8\"><title>Chatless</title></head><body><p>{}</p></body></html>",
+      message
+    );
+    let response = format!(
+      "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+      status,
+      body.len(),
+      body
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+    let _ = socket.shutdown().await;
+
+    match result {
+      Ok(None) => continue,
+      Ok(Some(code)) => return Ok(code),
+      Err(e) => return Err(e),
+    }
+  }
+}
+
+/// 优先复用上次的回调端口，使已注册的 redirect_uri 继续有效
+async fn bind_loopback(previous: Option<&StoredAuth>) -> Result<TcpListener, String> {
+  if let Some(port) = previous
+    .and_then(|a| Url::parse(&a.redirect_uri).ok())
+    .and_then(|u| u.port())
+  {
+    if let Ok(listener) = TcpListener::bind(("127.0.0.1", port)).await {
+      return Ok(listener);
+    }
+  }
+  TcpListener::bind(("127.0.0.1", 0))
+    .await
+    .map_err(|e| format!("Failed to bind loopback listener: {}", e))
+}
+
+/// 完整的授权码 + PKCE 流程：发现 → (动态注册) → 打开浏览器 → 回环接收 → 换取令牌 → 持久化
+pub(crate) async fn authorize(
+  app: &AppHandle,
+  client: &Client,
+  server_name: &str,
+  base_url: &str,
+) -> Result<StoredAuth, String> {
+  let base = Url::parse(base_url).map_err(|e| format!("Invalid baseUrl: {}", e))?;
+  let (meta, scopes) = discover(client, &base).await;
+
+  let previous = load_auth(app, server_name);
+  let listener = bind_loopback(previous.as_ref()).await?;
+  let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+  let redirect_uri = format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH);
+
+  // 回调地址与令牌端点均未变化时沿用已注册的客户端，否则重新注册
+  let (client_id, client_secret) = match previous
+    .as_ref()
+    .filter(|p| p.redirect_uri == redirect_uri && p.token_endpoint == meta.token_endpoint)
+  {
+    Some(p) => (p.client_id.clone(), p.client_secret.clone()),
+    None => {
+      let endpoint = meta.registration_endpoint.as_deref().ok_or_else(|| {
+        "Authorization server does not support dynamic client registration".to_string()
+      })?;
+      let reg = register_client(client, endpoint, &redirect_uri).await?;
+      log::info!(
+        "[MCP/auth] Registered client {} for {}",
+        reg.client_id,
+        server_name
+      );
+      (reg.client_id, reg.client_secret)
+    }
+  };
+
+  let verifier = random_token();
+  let state = random_token();
+  let scope = scopes.map(|s| s.join(" ")).filter(|s| !s.is_empty());
+
+  let mut auth_url = Url::parse(&meta.authorization_endpoint)
+    .map_err(|e| format!("Invalid authorization endpoint: {}", e))?;
+  {
+    let mut q = auth_url.query_pairs_mut();
+    q.append_pair("response_type", "code")
+      .append_pair("client_id", &client_id)
+      .append_pair("redirect_uri", &redirect_uri)
+      .append_pair("code_challenge", &pkce_challenge(&verifier))
+      .append_pair("code_challenge_method", "S256")
+      .append_pair("state", &state)
+      .append_pair("resource", base_url);
+    if let Some(scope) = &scope {
+      q.append_pair("scope", scope);
+    }
+  }
+
+  log::info!("[MCP/auth] Opening browser for server {}", server_name);
+  app
+    .opener()
+    .open_url(auth_url.as_str(), None::<&str>)
+    .map_err(|e| format!("Failed to open browser: {}", e))?;
+
+  let code = timeout(CALLBACK_TIMEOUT, wait_for_callback(listener, &state))
+    .await
+    .map_err(|_| "Timed out waiting for authorization".to_string())??;
+
+  let mut form = vec![
+    ("grant_type", "authorization_code"),
+    ("code", code.as_str()),
+    ("redirect_uri", redirect_uri.as_str()),
+    ("client_id", client_id.as_str()),
+    ("code_verifier", verifier.as_str()),
+    ("resource", base_url),
+  ];
+  if let Some(secret) = &client_secret {
+    form.push(("client_secret", secret.as_str()));
+  }
+  let token = request_token(client, &meta.token_endpoint, &form).await?;
+
+  let auth = StoredAuth {
+    resource: base_url.to_string(),
+    client_id,
+    client_secret,
+    redirect_uri,
+    token_endpoint: meta.token_endpoint,
+    access_token: token.access_token,
+    refresh_token: token.refresh_token,
+    expires_at: token.expires_in.map(|s| now_secs() + s),
+    scope: token.scope.or(scope),
+  };
+  save_auth(app, server_name, &auth)?;
+  log::info!("[MCP/auth] Server {} authorized", server_name);
+  Ok(auth)
+}
+
+async fn refresh(client: &Client, auth: &StoredAuth) -> Result<StoredAuth, String> {
+  let refresh_token = auth
+    .refresh_token
+    .as_deref()
+    .ok_or_else(|| "No refresh token".to_string())?;
+  let mut form = vec![
+    ("grant_type", "refresh_token"),
+    ("refresh_token", refresh_token),
+    ("client_id", auth.client_id.as_str()),
+    ("resource", auth.resource.as_str()),
+  ];
+  if let Some(secret) = &auth.client_secret {
+    form.push(("client_secret", secret.as_str()));
+  }
+  let token = request_token(client, &auth.token_endpoint, &form).await?;
+  Ok(StoredAuth {
+    access_token: token.access_token,
+    // 授权服务器可能不轮换 refresh token
+    refresh_token: token.refresh_token.or_else(|| auth.refresh_token.clone()),
+    expires_at: token.expires_in.map(|s| now_secs() + s),
+    scope: token.scope.or_else(|| auth.scope.clone()),
+    ..auth.clone()
+  })
+}
+
+/// 连接前获取可用的 access token：即将过期时自动刷新；没有可用令牌时返回 None
+pub(crate) async fn access_token(
+  app: &AppHandle,
+  client: &Client,
+  server_name: &str,
+  base_url: &str,
+) -> Option<String> {
+  let auth = load_auth(app, server_name)?;
+  if auth.resource != base_url {
+    log::debug!(
+      "[MCP/auth] Stored token for {} was issued for a different URL, ignoring",
+      server_name
+    );
+    return None;
+  }
+  let expired = auth
+    .expires_at
+    .map(|t| t <= now_secs() + REFRESH_MARGIN_SECS)
+    .unwrap_or(false);
+  if !expired {
+    return Some(auth.access_token);
+  }
+
+  match refresh(client, &auth).await {
+    Ok(fresh) => {
+      log::info!("[MCP/auth] Refreshed access token for {}", server_name);
+      if let Err(e) = save_auth(app, server_name, &fresh) {
+        log::warn!("[MCP/auth] {}", e);
+      }
+      Some(fresh.access_token)
+    }
+    Err(e) => {
+      log::warn!(
+        "[MCP/auth] Token refresh failed for {}: {}; re-authorization required",
+        server_name,
+        e
+      );
+      None
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pkce_challenge_matches_rfc7636_vector() {
+    assert_eq!(
+      pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+      "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+    );
+  }
+
+  #[test]
+  fn test_parse_callback() {
+    assert_eq!(
+      parse_callback("/callback?code=abc&state=s1", "s1").unwrap(),
+      Some("abc".to_string())
+    );
+    assert_eq!(parse_callback("/favicon.ico", "s1").unwrap(), None);
+    // state 不匹配的请求不属于本次授权，忽略并继续等待
+    assert_eq!(
+      parse_callback("/callback?code=abc&state=other", "s1").unwrap(),
+      None
+    );
+    assert_eq!(
+      parse_callback("/callback?error=access_denied&state=other", "s1").unwrap(),
+      None
+    );
+    assert!(parse_callback("/callback?error=access_denied&state=s1", "s1").is_err());
+  }
+
+  #[test]
+  fn test_parse_resource_metadata() {
+    assert_eq!(
+      parse_resource_metadata(
+        r#"Bearer realm="mcp", resource_metadata="https://a.example/.well-known/oauth-protected-resource""#
+      )
+      .as_deref(),
+      Some("https://a.example/.well-known/oauth-protected-resource")
+    );
+    assert_eq!(
+      parse_resource_metadata("Bearer resource_metadata=https://a.example/meta, scope=x")
+        .as_deref(),
+      Some("https://a.example/meta")
+    );
+    assert_eq!(parse_resource_metadata(r#"Bearer realm="mcp""#), None);
+  }
+}
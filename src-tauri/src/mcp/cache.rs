@@ -0,0 +1,211 @@
+use crate::mcp::state::McpState;
+use rmcp::model::{PaginatedRequestParam, Prompt, Resource, ResourceTemplate, Tool};
+use rmcp::service::{Peer, RoleClient, ServiceError};
+use std::future::Future;
+
+/// 单次列表最多跟随的分页数，防止异常服务器返回循环 cursor
+const MAX_PAGES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListingKind {
+  Tools,
+  Resources,
+  Prompts,
+}
+
+/// 某服务器的 tools/resources/prompts 列表缓存；收到 list_changed 通知时按类别失效
+#[derive(Debug, Clone, Default)]
+pub struct ServerListings {
+  pub tools: Option<Vec<Tool>>,
+  pub resources: Option<Vec<Resource>>,
+  pub prompts: Option<Vec<Prompt>>,
+  /// 每次失效递增；拉取期间若发生失效，则丢弃本次结果而不写入缓存
+  version: u64,
+}
+
+/// 跟随 next_cursor 拉取全部分页
+async fn collect_pages<T, F, Fut>(mut fetch: F) -> Result<Vec<T>, ServiceError>
+where
+  F: FnMut(Option<PaginatedRequestParam>) -> Fut,
+  Fut: Future<Output = Result<(Vec<T>, Option<String>), ServiceError>>,
+{
+  let mut items = Vec::new();
+  let mut cursor: Option<String> = None;
+  for _ in 0..MAX_PAGES {
+    let param = cursor
+      .clone()
+      .map(|c| PaginatedRequestParam { cursor: Some(c) });
+    let (page, next) = fetch(param).await?;
+    items.extend(page);
+    match next {
+      Some(next) if Some(&next) != cursor.as_ref() && !next.is_empty() => cursor = Some(next),
+      _ => return Ok(items),
+    }
+  }
+  log::warn!("[MCP] Pagination stopped after {} pages", MAX_PAGES);
+  Ok(items)
+}
+
+pub(crate) async fn fetch_all_tools(peer: &Peer<RoleClient>) -> Result<Vec<Tool>, ServiceError> {
+  collect_pages(|p| {
+    let peer = peer.clone();
+    async move {
+      let r = peer.list_tools(p).await?;
+      Ok((r.tools, r.next_cursor))
+    }
+  })
+  .await
+}
+
+pub(crate) async fn fetch_all_resources(
+  peer: &Peer<RoleClient>,
+) -> Result<Vec<Resource>, ServiceError> {
+  collect_pages(|p| {
+    let peer = peer.clone();
+    async move {
+      let r = peer.list_resources(p).await?;
+      Ok((r.resources, r.next_cursor))
+    }
+  })
+  .await
+}
+
+pub(crate) async fn fetch_all_prompts(
+  peer: &Peer<RoleClient>,
+) -> Result<Vec<Prompt>, ServiceError> {
+  collect_pages(|p| {
+    let peer = peer.clone();
+    async move {
+      let r = peer.list_prompts(p).await?;
+      Ok((r.prompts, r.next_cursor))
+    }
+  })
+  .await
+}
+
+pub(crate) async fn fetch_all_resource_templates(
+  peer: &Peer<RoleClient>,
+) -> Result<Vec<ResourceTemplate>, ServiceError> {
+  collect_pages(|p| {
+    let peer = peer.clone();
+    async move {
+      let r = peer.list_resource_templates(p).await?;
+      Ok((r.resource_templates, r.next_cursor))
+    }
+  })
+  .await
+}
+
+fn version_of(state: &McpState, server_name: &str) -> u64 {
+  state
+    .listings
+    .get(server_name)
+    .map(|l| l.version)
+    .unwrap_or(0)
+}
+
+/// 仅当拉取期间未发生失效时写入缓存
+fn store_if_fresh(
+  state: &McpState,
+  server_name: &str,
+  version: u64,
+  apply: impl FnOnce(&mut ServerListings),
+) {
+  let mut entry = state.listings.entry(server_name.to_string()).or_default();
+  if entry.version == version {
+    apply(&mut entry);
+  }
+}
+
+/// 获取工具列表：优先读缓存，`force_refresh` 或缓存缺失时重新拉取全部分页
+pub(crate) async fn tools(
+  state: &McpState,
+  server_name: &str,
+  force_refresh: bool,
+) -> Result<Vec<Tool>, String> {
+  if !force_refresh {
+    if let Some(tools) = state
+      .listings
+      .get(server_name)
+      .and_then(|l| l.tools.clone())
+    {
+      return Ok(tools);
+    }
+  }
+  let peer = state.peer(server_name)?;
+  let version = version_of(state, server_name);
+  let tools = fetch_all_tools(&peer).await.map_err(|e| e.to_string())?;
+  store_if_fresh(state, server_name, version, |l| {
+    l.tools = Some(tools.clone())
+  });
+  Ok(tools)
+}
+
+pub(crate) async fn resources(
+  state: &McpState,
+  server_name: &str,
+  force_refresh: bool,
+) -> Result<Vec<Resource>, String> {
+  if !force_refresh {
+    if let Some(resources) = state
+      .listings
+      .get(server_name)
+      .and_then(|l| l.resources.clone())
+    {
+      return Ok(resources);
+    }
+  }
+  let peer = state.peer(server_name)?;
+  let version = version_of(state, server_name);
+  let resources = fetch_all_resources(&peer)
+    .await
+    .map_err(|e| e.to_string())?;
+  store_if_fresh(state, server_name, version, |l| {
+    l.resources = Some(resources.clone())
+  });
+  Ok(resources)
+}
+
+pub(crate) async fn prompts(
+  state: &McpState,
+  server_name: &str,
+  force_refresh: bool,
+) -> Result<Vec<Prompt>, String> {
+  if !force_refresh {
+    if let Some(prompts) = state
+      .listings
+      .get(server_name)
+      .and_then(|l| l.prompts.clone())
+    {
+      return Ok(prompts);
+    }
+  }
+  let peer = state.peer(server_name)?;
+  let version = version_of(state, server_name);
+  let prompts = fetch_all_prompts(&peer).await.map_err(|e| e.to_string())?;
+  store_if_fresh(state, server_name, version, |l| {
+    l.prompts = Some(prompts.clone())
+  });
+  Ok(prompts)
+}
+
+/// 使某一类列表缓存失效
+pub(crate) fn invalidate(state: &McpState, server_name: &str, kind: ListingKind) {
+  let mut entry = state.listings.entry(server_name.to_string()).or_default();
+  entry.version += 1;
+  match kind {
+    ListingKind::Tools => entry.tools = None,
+    ListingKind::Resources => entry.resources = None,
+    ListingKind::Prompts => entry.prompts = None,
+  }
+}
+
+/// 清空某服务器的全部列表缓存（断开或重连时调用）
+pub(crate) fn invalidate_all(state: &McpState, server_name: &str) {
+  let mut entry = state.listings.entry(server_name.to_string()).or_default();
+  let version = entry.version + 1;
+  *entry = ServerListings {
+    version,
+    ..Default::default()
+  };
+}
@@ -0,0 +1,166 @@
+use crate::mcp::state::McpState;
+use dashmap::mapref::entry::Entry;
+use rmcp::model::{
+  CallToolRequest, CallToolRequestParam, CallToolResult, CancelledNotificationParam, ClientRequest,
+  ServerResult,
+};
+use rmcp::service::{Peer, PeerRequestOptions, RequestHandle, RoleClient};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::oneshot;
+use tokio::time::Duration;
+
+/// 工具调用开始时推送给前端的事件名（携带 callId，供 `mcp_cancel_call` 使用）
+pub const CALL_STARTED_EVENT: &str = "mcp-tool-call-started";
+/// 调用因并发或速率限制排队时推送的事件（开始执行后仍会推送 started，并带上实际等待时长）
+pub const CALL_QUEUED_EVENT: &str = "mcp-tool-call-queued";
+
+static CALL_SEQ: AtomicU64 = AtomicU64::new(1);
+/// 每次登记调用的唯一标记，清理时据此确认条目仍属于本次调用
+static CALL_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+/// 正在进行中的工具调用
+pub struct PendingCall {
+  pub server_name: String,
+  pub tool_name: String,
+  token: u64,
+  cancel_tx: oneshot::Sender<String>,
+}
+
+impl PendingCall {
+  /// 触发取消；reason 会随 MCP 取消通知发给服务器
+  pub fn cancel(self, reason: String) {
+    let _ = self.cancel_tx.send(reason);
+  }
+}
+
+pub(crate) fn next_call_id() -> String {
+  let epoch_ms = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_millis())
+    .unwrap_or(0);
+  format!(
+    "call-{}-{}",
+    epoch_ms,
+    CALL_SEQ.fetch_add(1, Ordering::SeqCst)
+  )
+}
+
+/// 登记一次工具调用，使其可以被 `mcp_cancel_call` 取消。
+/// 在排队等待限流之前调用，排队中的调用同样可以取消；同一 callId 已有进行中的调用时返回错误。
+/// 返回的标记交给 `unregister` 用于清理。
+pub(crate) fn register(
+  state: &McpState,
+  call_id: &str,
+  server_name: &str,
+  tool_name: &str,
+) -> Result<(u64, oneshot::Receiver<String>), String> {
+  let (cancel_tx, cancel_rx) = oneshot::channel::<String>();
+  let token = CALL_TOKEN.fetch_add(1, Ordering::SeqCst);
+  match state.pending_calls.entry(call_id.to_string()) {
+    Entry::Occupied(_) => Err(format!("Tool call {} is already in progress", call_id)),
+    Entry::Vacant(slot) => {
+      slot.insert(PendingCall {
+        server_name: server_name.to_string(),
+        tool_name: tool_name.to_string(),
+        token,
+        cancel_tx,
+      });
+      Ok((token, cancel_rx))
+    }
+  }
+}
+
+/// 调用结束后移除登记；已被取消（条目已移除）时不要误删之后复用该 callId 的调用
+pub(crate) fn unregister(state: &McpState, call_id: &str, token: u64) {
+  state
+    .pending_calls
+    .remove_if(call_id, |_, call| call.token == token);
+}
+
+/// 发起一次可超时、可取消的 tools/call（调用方需先通过 `register` 登记并传入取消信号）。
+/// 超时或被取消时会向服务器发送 `notifications/cancelled`，并丢弃等待中的响应。
+pub(crate) async fn call_tool_cancellable(
+  state: &McpState,
+  peer: &Peer<RoleClient>,
+  call_id: &str,
+  server_name: &str,
+  param: CallToolRequestParam,
+  timeout: Option<Duration>,
+  cancel_rx: oneshot::Receiver<String>,
+) -> Result<CallToolResult, String> {
+  let tool_name = param.name.to_string();
+  let result = run_call(state, peer, server_name, call_id, param, timeout, cancel_rx).await;
+  if let Err(e) = &result {
+    log::warn!(
+      "[MCP] Tool call {} ({} on {}) ended without result: {}",
+      call_id,
+      tool_name,
+      server_name,
+      e
+    );
+  }
+  result
+}
+
+async fn run_call(
+  state: &McpState,
+  peer: &Peer<RoleClient>,
+  server_name: &str,
+  call_id: &str,
+  param: CallToolRequestParam,
+  timeout: Option<Duration>,
+  cancel_rx: oneshot::Receiver<String>,
+) -> Result<CallToolResult, String> {
+  let request = ClientRequest::CallToolRequest(CallToolRequest::new(param));
+  let handle = peer
+    .send_cancellable_request(request, PeerRequestOptions::no_options())
+    .await
+    .map_err(|e| e.to_string())?;
+
+  // 服务器的进度通知只带 progressToken，登记映射以便前端按 callId 关联
+  let progress_key = (server_name.to_string(), handle.progress_token.clone());
+  state
+    .progress_calls
+    .insert(progress_key.clone(), call_id.to_string());
+  let result = wait_for_result(peer, handle, timeout, cancel_rx).await;
+  state.progress_calls.remove(&progress_key);
+  result
+}
+
+async fn wait_for_result(
+  peer: &Peer<RoleClient>,
+  handle: RequestHandle<RoleClient>,
+  timeout: Option<Duration>,
+  cancel_rx: oneshot::Receiver<String>,
+) -> Result<CallToolResult, String> {
+  let request_id = handle.id.clone();
+
+  // 未指定超时则一直等待（与旧行为一致），但仍可被取消
+  let deadline = async {
+    match timeout {
+      Some(d) => tokio::time::sleep(d).await,
+      None => std::future::pending::<()>().await,
+    }
+  };
+
+  let cancel_reason = tokio::select! {
+    res = handle.rx => {
+      return match res {
+        Ok(Ok(ServerResult::CallToolResult(r))) => Ok(r),
+        Ok(Ok(other)) => Err(format!("Unexpected response: {:?}", other)),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("Transport closed".to_string()),
+      };
+    }
+    reason = cancel_rx => reason.unwrap_or_else(|_| "cancelled".to_string()),
+    _ = deadline => format!("timeout after {}ms", timeout.map(|d| d.as_millis()).unwrap_or(0)),
+  };
+
+  let _ = peer
+    .notify_cancelled(CancelledNotificationParam {
+      request_id,
+      reason: Some(cancel_reason.clone()),
+    })
+    .await;
+  Err(format!("Tool call cancelled: {}", cancel_reason))
+}
@@ -1,106 +1,45 @@
-use crate::env_setup::EnvironmentSetup;
-use crate::mcp::state::{McpService, McpState};
-use crate::mcp::types::McpServerConfig;
-use rmcp::{
-  model::{
-    CallToolRequestParam, GetPromptRequestParam, ListToolsResult, ReadResourceRequestParam, Tool,
-  },
-  service::ServiceExt,
-  transport::{
-    sse_client::{SseClientConfig, SseClientTransport},
-    streamable_http_client::{StreamableHttpClientTransport, StreamableHttpClientTransportConfig},
-    ConfigureCommandExt, TokioChildProcess,
-  },
+use crate::mcp::arguments;
+use crate::mcp::audit::{self, McpAuditEntry, McpAuditQuery};
+use crate::mcp::auth::{self, McpAuthStatus};
+use crate::mcp::cache;
+use crate::mcp::calls;
+use crate::mcp::connect::{connect_server, remote_client};
+use crate::mcp::diagnose::{self, McpDiagnosticReport};
+use crate::mcp::elicitation::{self, McpElicitationResponse};
+use crate::mcp::expand;
+use crate::mcp::import::{self, McpImportPreview};
+use crate::mcp::local_server::McpKbQueryResponse;
+use crate::mcp::policy::{self, LoadedPolicy, PolicyDecision, StdioPolicy};
+use crate::mcp::registry::{self, McpQualifiedTool, McpToolFilter};
+use crate::mcp::results::{self, McpTextChunk};
+use crate::mcp::roots;
+use crate::mcp::sampling::McpSamplingResponse;
+use crate::mcp::schema;
+use crate::mcp::state::McpState;
+use crate::mcp::supervisor;
+use crate::mcp::tool_policy::{self, McpServerToolPolicies, ToolPolicy};
+use crate::mcp::types::{
+  McpConnectionInfo, McpRootInput, McpServerConfig, McpServerInfo, McpServerStatus,
+  McpServerStatusInfo,
 };
-// use std::sync::Arc; // no longer needed after using with_uri
-use tauri::State;
-use tokio::process::Command;
-use tokio::time::{timeout, Duration};
-use reqwest::Url;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-
-// 仅在Windows平台编译时，引入 CommandExt Trait
-#[cfg(windows)]
-use std::os::windows::process::CommandExt;
-
-// —— 工具：从 npx 参数中提取第一个包名（用于首次安装的预拉取） ——
-fn extract_npx_package(args: &Option<Vec<String>>) -> Option<String> {
-  if let Some(a) = args {
-    for it in a {
-      // 跳过常见的 flags（以 - 开头）
-      if it.starts_with('-') {
-        continue;
-      }
-      return Some(it.clone());
-    }
-  }
-  None
-}
-
-async fn npx_prefetch_package(pkg: &str) -> Result<(), String> {
-  // 只下载依赖，不启动 MCP；最长等待 4 分钟以适配首次安装
-  let mut pre = Command::new("npx");
-
-  // [关键步骤] 为Windows平台设置无窗口创建标志
-  #[cfg(windows)]
-  {
-    pre.creation_flags(0x08000000); // CREATE_NO_WINDOW
-  }
-
-  pre.env("NPM_CONFIG_LOGLEVEL", "silent");
-  pre.env("NO_COLOR", "1");
-  pre.env("NPX_Y", "1");
-  pre.args(["-y", "-p", pkg, "node", "-e", "process.exit(0)"]);
-  log::info!("[MCP/npx] prefetch package: {}", pkg);
-  let status = timeout(Duration::from_secs(240), pre.status())
-    .await
-    .map_err(|_| "npx prefetch timeout".to_string())?
-    .map_err(|e| e.to_string())?;
-  if !status.success() {
-    return Err(format!("npx prefetch failed with code {:?}", status.code()));
-  }
-  Ok(())
-}
-
-fn is_path_like(arg: &str) -> bool {
-  if arg.is_empty() {
-    return false;
-  }
-  let lower = arg.to_lowercase();
-  if lower.starts_with("http://") || lower.starts_with("https://") {
-    return false;
-  }
-  // npm scope 包名，如 @scope/name，不应被当作路径
-  if arg.starts_with('@') {
-    // 典型包名不包含反斜杠和盘符
-    if arg.contains('/') && !arg.contains('\\') && !arg.contains(':') {
-      return false;
-    }
-  }
-  if arg.starts_with('/')
-    || arg.starts_with("./")
-    || arg.starts_with("../")
-    || arg.starts_with("~/")
-  {
-    return true;
-  }
-  // Windows 盘符，如 C:\ 或 C:/
-  if arg.len() >= 3 {
-    let bytes = arg.as_bytes();
-    if bytes[1] == b':' && (bytes[2] == b'/' || bytes[2] == b'\\') {
-      return true;
-    }
-  }
-  // 包含路径分隔符的其它情况
-  arg.contains('/') || arg.contains('\\')
-}
+use rmcp::model::{
+  CallToolRequestParam, GetPromptRequestParam, ReadResourceRequestParam, ResourceTemplate, Root,
+  SubscribeRequestParam, Tool, UnsubscribeRequestParam,
+};
+use rmcp::service::{Peer, RoleClient};
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::oneshot;
+use tokio::time::Duration;
 
 #[tauri::command]
 pub async fn mcp_connect(
+  app: AppHandle,
   name: String,
   config: McpServerConfig,
+  confirmed: Option<bool>,
   state: State<'_, McpState>,
-) -> Result<(), String> {
+) -> Result<McpConnectionInfo, String> {
   log::info!(
     "[MCP] Starting connection to server: {} with type: {}",
     name,
@@ -108,9 +47,18 @@ pub async fn mcp_connect(
   );
   log::debug!("[MCP] Full config: {:?}", config);
 
-  if state.0.contains_key(&name) {
+  if state.services.contains_key(&name) {
     log::info!("[MCP] Server {} already connected, skipping", name);
-    return Ok(());
+    let transport = state
+      .servers
+      .get(&name)
+      .and_then(|e| e.transport.clone())
+      .unwrap_or_else(|| config.r#type.clone());
+    return Ok(McpConnectionInfo {
+      server_name: name,
+      transport,
+      already_connected: true,
+    });
   }
 
   // 检查环境是否支持 MCP 服务
@@ -128,571 +76,53 @@ pub async fn mcp_connect(
     return Err(error_msg);
   }
 
-  match config.r#type.as_str() {
-    "stdio" => {
-      log::info!(
-        "[MCP/stdio] Initializing stdio connection for server: {}",
-        name
-      );
-
-      let cmd_name = config
-        .command
-        .clone()
-        .ok_or_else(|| "command required for stdio".to_string())?;
-      log::info!("[MCP/stdio] Command name: {}", cmd_name);
-
-      // —— 基础安全校验：仅允许白名单命令或显式路径；另允许 Windows 包装器 cmd /c ——
-      let is_path = cmd_name.contains('/') || cmd_name.contains('\\');
-      log::debug!("[MCP/stdio] Command is_path: {}", is_path);
-
-      if !is_path {
-        const ALLOW: [&str; 3] = ["npx", "uvx", "bunx"];
-        let is_wrapper_cmd = cmd_name.eq_ignore_ascii_case("cmd")
-          && config
-            .args
-            .as_ref()
-            .map(|a| {
-              a.get(0)
-                .map(|s| s.eq_ignore_ascii_case("/c"))
-                .unwrap_or(false)
-            })
-            .unwrap_or(false)
-          && config.args.as_ref().map(|a| a.len() >= 2).unwrap_or(false);
-
-        log::debug!("[MCP/stdio] Is wrapper cmd: {}", is_wrapper_cmd);
-        log::debug!("[MCP/stdio] Allowed commands: {:?}", ALLOW);
-
-        if !ALLOW.contains(&cmd_name.as_str()) && !is_wrapper_cmd {
-          let error_msg = format!(
-            "command '{}' is not allowed. use one of: npx, uvx, bunx, an absolute path, or Windows wrapper 'cmd /c <cmd>'",
-            cmd_name
-          );
-          log::error!("[MCP/stdio] Security check failed: {}", error_msg);
-          return Err(error_msg);
-        }
+  // 登记到 supervisor，保存原始配置以便断线后按同一配置重连；
+  // 之前的登记（如正在重连的同名服务器）在需要确认时恢复
+  let previous = state.servers.get(&name).map(|e| e.clone());
+  let generation = supervisor::register(&app, &state, &name, config.clone());
+
+  // stdio 策略在展开后的配置上检查；要求确认时需前端以 confirmed=true 重新调用
+  match connect_server(&app, &name, &config, confirmed.unwrap_or(false)).await {
+    Ok((service, transport)) => {
+      let server_info = service.peer().peer_info().cloned();
+      state.services.insert(name.clone(), service);
+      if let Some(mut e) = state.servers.get_mut(&name) {
+        e.transport = Some(transport.to_string());
+        e.server_info = server_info;
       }
-
-      // 构造命令的闭包，便于重试
-      let build_cmd = || {
-        log::debug!("[MCP/stdio] Building command: {}", cmd_name);
-
-        let mut c = Command::new(&cmd_name);
-
-        // [关键步骤] 为Windows平台设置无窗口创建标志
-        #[cfg(windows)]
-        {
-          c.creation_flags(0x08000000); // CREATE_NO_WINDOW
-          log::debug!("[MCP/stdio] Set Windows CREATE_NO_WINDOW flag");
-        }
-
-        // 动态设置环境变量，特别是对于 npm 相关命令
-        if cmd_name == "npx" || cmd_name == "npm" || cmd_name == "node" {
-          log::debug!(
-            "[MCP/stdio] Setting up environment for {} command",
-            cmd_name
-          );
-
-          // 获取当前环境设置（用于调试）
-          let _current_path = std::env::var("PATH").unwrap_or_default();
-
-          // 如果检测到工具不可用，尝试重新设置环境
-          let env_checker = EnvironmentSetup::new();
-          if !env_checker.verify_tool_availability(&cmd_name) {
-            log::warn!(
-              "[MCP/stdio] Tool {} not found in current PATH, attempting to refresh environment",
-              cmd_name
-            );
-
-            // 尝试设置环境变量
-            if let Ok(()) = EnvironmentSetup::new().setup() {
-              // 重新获取环境设置以获取更新后的 PATH
-              let updated_env = EnvironmentSetup::new();
-              let updated_path = updated_env.get_updated_path();
-              c.env("PATH", updated_path);
-              log::info!("[MCP/stdio] Updated PATH for {} command", cmd_name);
-            } else {
-              log::warn!(
-                "[MCP/stdio] Failed to setup environment for {} command",
-                cmd_name
-              );
-            }
-          } else {
-            log::debug!("[MCP/stdio] Tool {} is available in current PATH", cmd_name);
-          }
-        }
-
-        if let Some(args) = &config.args {
-          c.args(args);
-          log::debug!("[MCP/stdio] Added args: {:?}", args);
-        }
-
-        if let Some(envs) = &config.env {
-          for (k, v) in envs {
-            c.env(k, v);
-            log::debug!("[MCP/stdio] Set env: {}={}", k, v);
-          }
-        }
-
-        if cmd_name == "npx" {
-          c.env("NPM_CONFIG_LOGLEVEL", "silent");
-          c.env("NO_COLOR", "1");
-          c.env("NPX_Y", "1");
-          log::debug!("[MCP/stdio] Set npx-specific environment variables");
-        }
-
-        // Log the final command for debugging
-        log::info!("[MCP/stdio] Built command: {} {:?}", cmd_name, config.args);
-
-        c
-      };
-
-      // —— 参数校验，避免简单的 shell 注入字符 ——
-      if let Some(args) = &config.args {
-        log::debug!("[MCP/stdio] Validating arguments: {:?}", args);
-
-        let joined = args.join(" ");
-        if joined.len() > 2048 {
-          let error_msg = "args too long".to_string();
-          log::error!("[MCP/stdio] Args validation failed: {}", error_msg);
-          return Err(error_msg);
-        }
-
-        if joined.contains('|')
-          || joined.contains('&')
-          || joined.contains(';')
-          || joined.contains('>')
-          || joined.contains('<')
-        {
-          let error_msg = "args contains forbidden shell characters".to_string();
-          log::error!("[MCP/stdio] Args validation failed: {}", error_msg);
-          return Err(error_msg);
-        }
-
-        // 通用路径存在性校验：检测看起来像路径的参数，如果不存在则直接提示（避免特定 MCP 魔法处理）
-        // 规则：从第一个非 flag 参数（一般是包名）之后的参数中筛选
-        // Windows 包装器 cmd /c <cmd> <args...> 需要跳过前两个参数
-        let wrapper_offset = if cmd_name.eq_ignore_ascii_case("cmd")
-          && args
-            .get(0)
-            .map(|s| s.eq_ignore_ascii_case("/c"))
-            .unwrap_or(false)
-          && args.len() >= 2
-        {
-          2
-        } else {
-          0
-        };
-
-        log::debug!("[MCP/stdio] Wrapper offset: {}", wrapper_offset);
-
-        let args_slice = &args[wrapper_offset..];
-        let mut first_non_flag_in_slice: Option<usize> = None;
-        for (i, it) in args_slice.iter().enumerate() {
-          if !it.starts_with('-') {
-            first_non_flag_in_slice = Some(i);
-            break;
-          }
-        }
-
-        if let Some(mut idx) = first_non_flag_in_slice {
-          log::debug!("[MCP/stdio] First non-flag arg index: {}", idx);
-
-          // 特判：cmd /c + npx/uvx/bunx 的形式。此时 idx 指向的是执行器（npx），
-          // 需要继续向后找到真正的包名（第一个非 flag），并从包名之后开始校验路径。
-          if args_slice
-            .get(idx)
-            .map(|s| s.as_str())
-            .map(|s| {
-              s.eq_ignore_ascii_case("npx")
-                || s.eq_ignore_ascii_case("uvx")
-                || s.eq_ignore_ascii_case("bunx")
-            })
-            .unwrap_or(false)
-          {
-            let mut pkg_idx_in_slice: Option<usize> = None;
-            for (j, it) in args_slice.iter().enumerate().skip(idx + 1) {
-              if !it.starts_with('-') {
-                pkg_idx_in_slice = Some(j);
-                break;
-              }
-            }
-            if let Some(pidx) = pkg_idx_in_slice {
-              idx = pidx;
-              log::debug!("[MCP/stdio] Adjusted package index to: {}", idx);
-            }
-          }
-
-          let start = wrapper_offset + idx + 1; // 从包名后的参数开始
-          log::debug!("[MCP/stdio] Path validation start index: {}", start);
-
-          if start < args.len() {
-            let mut missing: Vec<String> = Vec::new();
-            for raw in &args[start..] {
-              if is_path_like(raw) {
-                log::debug!("[MCP/stdio] Checking path-like argument: {}", raw);
-                // 直接按原样检查（不展开 ~ 等），以避免误判和隐式替换
-                match tokio::fs::metadata(raw).await {
-                  Ok(metadata) => {
-                    log::debug!(
-                      "[MCP/stdio] Path exists: {} (type: {:?})",
-                      raw,
-                      metadata.file_type()
-                    );
-                  }
-                  Err(e) => {
-                    log::warn!("[MCP/stdio] Path does not exist: {} (error: {})", raw, e);
-                    missing.push(raw.clone());
-                  }
-                }
-              }
-            }
-
-            if !missing.is_empty() {
-              let mut msg = format!("Path arguments do not exist: {}", missing.join(", "));
-              let placeholder_hits: Vec<&str> = missing
-                .iter()
-                .filter_map(|s| {
-                  let ls = s.to_lowercase();
-                  if ls.contains("/users/username/") || ls.contains("path/to/other/allowed/dir") {
-                    Some(s.as_str())
-                  } else {
-                    None
-                  }
-                })
-                .collect();
-              if !placeholder_hits.is_empty() {
-                msg.push_str(". It looks like placeholder paths are still present. Please replace them with real existing directories.");
-              }
-              log::error!("[MCP/stdio] Path validation failed: {}", msg);
-              return Err(msg);
-            }
-          }
-        }
-      }
-
-      // —— 调试日志 ——
-      log::info!("[MCP/stdio] Command validation passed, spawning process");
-      log::debug!(
-        "[MCP/stdio] Final command details: cmd='{}' args={:?} envs={}",
-        cmd_name,
-        &config.args,
-        config.env.as_ref().map(|v| v.len()).unwrap_or(0)
-      );
-
-      // 一次尝试的封装
-      let try_connect = || async {
-        log::debug!("[MCP/stdio] Attempting to spawn child process");
-        let cmd = build_cmd();
-
-        // Log current working directory and environment for debugging
-        if let Ok(current_dir) = std::env::current_dir() {
-          log::debug!("[MCP/stdio] Current working directory: {:?}", current_dir);
-        }
-
-        if let Ok(path) = std::env::var("PATH") {
-          log::debug!("[MCP/stdio] PATH environment: {}", path);
-        }
-
-        match TokioChildProcess::new(cmd.configure(|_c| {})) {
-          Ok(process) => {
-            log::debug!("[MCP/stdio] Child process created successfully");
-            let service: McpService = ().serve(process).await.map_err(|e| {
-              log::error!("[MCP/stdio] Service creation failed: {}", e);
-              e.to_string()
-            })?;
-            log::info!("[MCP/stdio] MCP service created successfully");
-            Ok::<McpService, String>(service)
-          }
-          Err(e) => {
-            log::error!("[MCP/stdio] Failed to create child process: {}", e);
-            Err(e.to_string())
-          }
-        }
-      };
-
-      // 第一次尝试（可能在 npx 首次下载时失败/超时）
-      log::info!("[MCP/stdio] Starting first connection attempt with 30s timeout");
-      let first = timeout(Duration::from_secs(30), try_connect())
-        .await
-        .map_err(|_| {
-          log::error!("[MCP/stdio] First connection attempt timed out");
-          "Connect timeout (stdio)".to_string()
-        });
-
-      match first {
-        Ok(Ok(service)) => {
-          log::info!(
-            "[MCP/stdio] First connection attempt successful for server: {}",
-            name
-          );
-          state.0.insert(name, service);
-          Ok(())
-        }
-        Ok(Err(e)) | Err(e) => {
-          log::warn!("[MCP/stdio] First connection attempt failed: {}", e);
-
-          if cmd_name == "npx" {
-            if let Some(pkg) = extract_npx_package(&config.args) {
-              log::info!(
-                "[MCP/stdio] First connect failed: {}. prefetching {}...",
-                e,
-                pkg
-              );
-              // 预下载失败则直接返回组合错误
-              match npx_prefetch_package(&pkg).await {
-                Ok(()) => {
-                  log::info!("[MCP/stdio] Package prefetch successful, retrying connection");
-                }
-                Err(pe) => {
-                  log::error!("[MCP/stdio] Package prefetch failed: {}", pe);
-                  return Err(format!("{}; prefetch: {}", e, pe));
-                }
-              }
-
-              // 预下载成功后重试
-              log::info!("[MCP/stdio] Starting second connection attempt after prefetch");
-              let second = timeout(Duration::from_secs(30), try_connect())
-                .await
-                .map_err(|_| {
-                  log::error!("[MCP/stdio] Second connection attempt timed out");
-                  "Connect timeout (stdio, after prefetch)".to_string()
-                })??;
-
-              log::info!(
-                "[MCP/stdio] Second connection attempt successful for server: {}",
-                name
-              );
-              state.0.insert(name, second);
-              Ok(())
-            } else {
-              log::error!("[MCP/stdio] Failed to extract package name from npx args");
-              Err(e)
-            }
-          } else {
-            log::error!("[MCP/stdio] Connection failed and not using npx, cannot retry");
-            Err(e)
-          }
-        }
-      }
-    }
-    "sse" => {
-      log::info!("[MCP/sse] Initializing SSE connection for server: {}", name);
-
-      let base = config
-        .base_url
-        .clone()
-        .ok_or_else(|| "baseUrl required for sse".to_string())?;
-      log::info!("[MCP/sse] Connecting to baseUrl: {}", &base);
-
-      // —— 代理选择策略：若 use_proxy=true 且 proxy_url 存在，且目标非本地/私网，则使用带代理客户端；否则使用浏览器化客户端 ——
-      let should_use_proxy = {
-        let enabled = config.use_proxy.unwrap_or(false) && config.proxy_url.is_some();
-        if !enabled { false } else {
-          match Url::parse(&base) {
-            Ok(u) => {
-              let host = u.host_str().unwrap_or_default();
-              is_local_or_private(host) == false
-            }
-            Err(_) => {
-              // 无法解析 URL，保守起见不走代理
-              false
-            }
-          }
-        }
-      };
-
-      let req = if should_use_proxy {
-        let mut cfg = crate::http_client::HttpClientConfig::default();
-        cfg.http1_only = true;
-        cfg.gzip = false;
-        cfg.brotli = false;
-        cfg.proxy_url = config.proxy_url.clone();
-        match crate::http_client::HttpClientManager::build_custom_client(cfg) {
-          Ok(client) => {
-            log::debug!("[MCP/sse] Using custom proxied HTTP client");
-            std::sync::Arc::new(client)
-          }
-          Err(e) => {
-            log::error!("[MCP/sse] Failed to build proxied client: {}", e);
-            return Err(format!("Failed to build proxied client: {}", e));
-          }
-        }
-      } else {
-        match crate::http_client::get_browser_like_client() {
-          Ok(client) => {
-            log::debug!("[MCP/sse] Using browser-like HTTP client");
-            client
-          }
-          Err(e) => {
-            log::error!("[MCP/sse] Failed to get HTTP client: {}", e);
-            return Err(format!("Failed to get HTTP client: {}", e));
-          }
-        }
-      };
-
-      let req = (*req).clone(); // 从Arc<Client>转换为Client
-      let cfg = SseClientConfig {
-        sse_endpoint: base.into(),
-        ..Default::default()
-      };
-      log::debug!("[MCP/sse] SSE config: {:?}", cfg);
-
-      let transport = match SseClientTransport::start_with_client(req, cfg).await {
-        Ok(transport) => {
-          log::debug!("[MCP/sse] SSE transport started successfully");
-          transport
-        }
-        Err(e) => {
-          log::error!("[MCP/sse] Failed to start SSE transport: {}", e);
-          return Err(e.to_string());
-        }
-      };
-
-      let service: McpService = match ().serve(transport).await {
-        Ok(service) => {
-          log::info!(
-            "[MCP/sse] SSE service created successfully for server: {}",
-            name
-          );
-          service
-        }
-        Err(e) => {
-          log::error!("[MCP/sse] Failed to create SSE service: {}", e);
-          return Err(e.to_string());
-        }
-      };
-
-      state.0.insert(name.clone(), service);
-      log::info!(
-        "[MCP/sse] SSE connection established successfully for server: {}",
-        name
-      );
-      Ok(())
+      supervisor::set_status(&app, &state, &name, McpServerStatus::Ready, None);
+      supervisor::spawn_monitor(app.clone(), name.clone(), generation);
+      log::info!("[MCP] Server {} connected via {}", name, transport);
+      Ok(McpConnectionInfo {
+        server_name: name,
+        transport: transport.to_string(),
+        already_connected: false,
+      })
     }
-    "http" => {
-      log::info!(
-        "[MCP/http] Initializing HTTP connection for server: {}",
-        name
-      );
-
-      let base = config
-        .base_url
-        .clone()
-        .ok_or_else(|| "baseUrl required for http".to_string())?;
-      log::info!("[MCP/http] Connecting to baseUrl: {}", &base);
-
-      // —— 代理选择（同上） ——
-      let should_use_proxy = {
-        let enabled = config.use_proxy.unwrap_or(false) && config.proxy_url.is_some();
-        if !enabled { false } else {
-          match Url::parse(&base) {
-            Ok(u) => {
-              let host = u.host_str().unwrap_or_default();
-              is_local_or_private(host) == false
-            }
-            Err(_) => false
-          }
-        }
-      };
-
-      let req = if should_use_proxy {
-        let mut cfg = crate::http_client::HttpClientConfig::default();
-        cfg.http1_only = true;
-        cfg.gzip = false;
-        cfg.brotli = false;
-        cfg.proxy_url = config.proxy_url.clone();
-        match crate::http_client::HttpClientManager::build_custom_client(cfg) {
-          Ok(client) => {
-            log::debug!("[MCP/http] Using custom proxied HTTP client");
-            std::sync::Arc::new(client)
-          }
-          Err(e) => {
-            log::error!("[MCP/http] Failed to build proxied client: {}", e);
-            return Err(format!("Failed to build proxied client: {}", e));
-          }
-        }
-      } else {
-        match crate::http_client::get_browser_like_client() {
-          Ok(client) => {
-            log::debug!("[MCP/http] Using browser-like HTTP client");
-            client
-          }
-          Err(e) => {
-            log::error!("[MCP/http] Failed to get HTTP client: {}", e);
-            return Err(format!("Failed to get HTTP client: {}", e));
-          }
-        }
-      };
-
-      let req = (*req).clone(); // 从Arc<Client>转换为Client
-      let cfg = StreamableHttpClientTransportConfig::with_uri(base);
-      log::debug!("[MCP/http] HTTP transport config: {:?}", cfg);
-
-      let transport = StreamableHttpClientTransport::with_client(req, cfg);
-      log::debug!("[MCP/http] HTTP transport created successfully");
-
-      let service: McpService = match ().serve(transport).await {
-        Ok(service) => {
-          log::info!(
-            "[MCP/http] HTTP service created successfully for server: {}",
-            name
-          );
-          service
-        }
-        Err(e) => {
-          log::error!("[MCP/http] Failed to create HTTP service: {}", e);
-          return Err(e.to_string());
-        }
-      };
-
-      state.0.insert(name.clone(), service);
-      log::info!(
-        "[MCP/http] HTTP connection established successfully for server: {}",
-        name
-      );
-      Ok(())
+    Err(e) if e.starts_with(policy::CONFIRMATION_REQUIRED) => {
+      // 尚未启动，等待用户确认：撤销本次的监管登记，避免显示为失败
+      log::info!("[MCP] Server {} requires user confirmation", name);
+      supervisor::unregister(&app, &state, &name, generation, previous);
+      Err(e)
     }
-    _ => {
-      let error_msg = format!("Unsupported transport type: {}", config.r#type);
-      log::error!("[MCP] {}", error_msg);
-      Err(error_msg)
+    Err(e) => {
+      supervisor::set_status(&app, &state, &name, McpServerStatus::Dead, Some(e.clone()));
+      Err(e)
     }
   }
 }
 
-/// 判断 host 是否为本地或私有网段（用于自动绕过代理）
-fn is_local_or_private(host: &str) -> bool {
-  let lower = host.to_ascii_lowercase();
-  if lower == "localhost" {
-    return true;
-  }
-  if let Ok(ip) = host.parse::<IpAddr>() {
-    match ip {
-      IpAddr::V4(v4) => {
-        if v4.is_loopback() || v4.is_private() {
-          return true;
-        }
-        // 额外常见内网广播/链路本地
-        if v4.octets()[0] == 169 && v4.octets()[1] == 254 {
-          return true;
-        }
-        false
-      }
-      IpAddr::V6(v6) => {
-        // 回环或链路本地
-        v6.is_loopback() || v6.is_unicast_link_local()
-      }
-    }
-  } else {
-    // 域名无法判定，视为非本地
-    false
-  }
-}
 #[tauri::command]
 pub async fn mcp_disconnect(name: String, state: State<'_, McpState>) -> Result<(), String> {
   log::info!("[MCP] Disconnecting server: {}", name);
 
-  if let Some((_, service)) = state.0.remove(&name) {
+  // 先移除监管信息，监控任务会在下一轮检查时退出，不再触发重连
+  state.servers.remove(&name);
+  cache::invalidate_all(&state, &name);
+  state.subscriptions.remove(&name);
+  state.child_pids.remove(&name);
+
+  if let Some((_, service)) = state.services.remove(&name) {
     log::debug!("[MCP] Found service, cancelling...");
     match service.cancel().await {
       Ok(_) => {
@@ -710,25 +140,53 @@ pub async fn mcp_disconnect(name: String, state: State<'_, McpState>) -> Result<
   }
 }
 
+/// 查询所有受监管 MCP 服务器的连接状态
+#[tauri::command]
+pub async fn mcp_get_server_statuses(
+  state: State<'_, McpState>,
+) -> Result<Vec<McpServerStatusInfo>, String> {
+  Ok(supervisor::snapshot(&state))
+}
+
+/// 读取服务器连接时在 initialize 中返回的信息（名称、版本、协议版本、能力与 instructions）。
+/// 前端可将 instructions 提供给模型，并隐藏服务器未声明的 prompts / resources 功能
+#[tauri::command]
+pub async fn mcp_get_server_info(
+  server_name: String,
+  state: State<'_, McpState>,
+) -> Result<McpServerInfo, String> {
+  let entry = state
+    .servers
+    .get(&server_name)
+    .ok_or_else(|| "Server not found".to_string())?;
+  let initialize = entry
+    .server_info
+    .clone()
+    .ok_or_else(|| format!("Server {} has not completed initialization", server_name))?;
+  Ok(McpServerInfo::new(
+    server_name,
+    entry.transport.clone(),
+    initialize,
+  ))
+}
+
+/// 列出工具（自动跟随分页，结果按服务器缓存；`force_refresh` 为 true 时绕过缓存）
 #[tauri::command]
 pub async fn mcp_list_tools(
   server_name: String,
+  force_refresh: Option<bool>,
   state: State<'_, McpState>,
 ) -> Result<Vec<Tool>, String> {
   log::debug!("[MCP] Listing tools for server: {}", server_name);
 
-  let service = state
-    .0
-    .get(&server_name)
-    .ok_or_else(|| "Server not found".to_string())?;
-  let ListToolsResult { tools, .. } = match service.list_tools(Default::default()).await {
-    Ok(result) => {
+  match cache::tools(&state, &server_name, force_refresh.unwrap_or(false)).await {
+    Ok(tools) => {
       log::debug!(
         "[MCP] Successfully listed {} tools from server {}",
-        result.tools.len(),
+        tools.len(),
         server_name
       );
-      result
+      Ok(tools)
     }
     Err(e) => {
       log::error!(
@@ -736,37 +194,201 @@ pub async fn mcp_list_tools(
         server_name,
         e
       );
-      return Err(e.to_string());
+      Err(e)
     }
-  };
-
-  Ok(tools)
+  }
 }
 
+/// 调用 MCP 工具
+///
+/// * `call_id`    – Optional: 前端指定的调用 ID（未传则自动生成），可用于 `mcp_cancel_call`
+/// * `timeout_ms` – Optional: 超时毫秒数；超时后向服务器发送取消通知并返回错误
+///
+/// * `confirmed`  – Optional: 工具策略为 ask 时，用户确认后以 true 重试
+///
+/// 服务器配置了 `limits` 时调用可能排队（推送 `mcp-tool-call-queued`），排队超时返回 `MCP_RATE_LIMITED:`。
+/// 调用前按工具策略放行：deny 返回 `MCP_TOOL_DENIED:`，未确认的 ask 返回 `MCP_TOOL_APPROVAL_REQUIRED:`。
+/// 参数会先按工具的 inputSchema 修正并校验，不合法时返回以 `MCP_INVALID_ARGUMENTS:` 开头的结构化错误。
+/// 结果中的二进制内容会落盘为缓存文件引用，超长文本会被截断（见 `mcp_read_result_text`）。
+/// 每次调用（含被拒绝的）都会写入审计日志（见 `mcp_query_audit_log`）
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn mcp_call_tool(
+  app: AppHandle,
   server_name: String,
   tool_name: String,
   args: Option<serde_json::Map<String, serde_json::Value>>,
+  call_id: Option<String>,
+  timeout_ms: Option<u64>,
+  confirmed: Option<bool>,
   state: State<'_, McpState>,
 ) -> Result<serde_json::Value, String> {
+  call_tool(
+    &app,
+    &state,
+    server_name,
+    tool_name,
+    args,
+    call_id,
+    timeout_ms,
+    confirmed.unwrap_or(false),
+  )
+  .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn call_tool(
+  app: &AppHandle,
+  state: &McpState,
+  server_name: String,
+  tool_name: String,
+  args: Option<serde_json::Map<String, serde_json::Value>>,
+  call_id: Option<String>,
+  timeout_ms: Option<u64>,
+  confirmed: bool,
+) -> Result<serde_json::Value, String> {
+  let call_id = call_id
+    .filter(|s| !s.is_empty())
+    .unwrap_or_else(calls::next_call_id);
   log::debug!(
-    "[MCP] Calling tool {} on server {} with args: {:?}",
+    "[MCP] Calling tool {} on server {} (callId={}, timeoutMs={:?}) with args: {:?}",
     tool_name,
     server_name,
+    call_id,
+    timeout_ms,
     args
   );
 
-  let service = state
-    .0
-    .get(&server_name)
-    .ok_or_else(|| "Server not found".to_string())?;
+  // 过滤设置同样约束直接调用，被隐藏的工具不能绕过限定名调用
+  registry::check_filter(app, &server_name, &tool_name)?;
+
+  let record = audit::CallRecord::start(&call_id, &server_name, &tool_name, args.as_ref());
+  let policy = tool_policy::load(app, &server_name).policy_for(&tool_name);
+  let decision = match tool_policy::decide(policy, &server_name, &tool_name, confirmed) {
+    Ok(d) => d,
+    Err(e) => {
+      log::info!("[MCP/tool-policy] {}", e);
+      // 等待用户确认的调用尚未发生，不记入审计日志
+      if e.starts_with(tool_policy::TOOL_DENIED) {
+        let outcome = Err(e);
+        audit::record(app, state, record, "denied", &outcome).await;
+        return outcome;
+      }
+      return Err(e);
+    }
+  };
+
+  let outcome = execute_tool(
+    app,
+    state,
+    &server_name,
+    &tool_name,
+    args,
+    &call_id,
+    timeout_ms,
+  )
+  .await;
+  audit::record(app, state, record, decision.as_str(), &outcome).await;
+  outcome
+}
+
+async fn execute_tool(
+  app: &AppHandle,
+  state: &McpState,
+  server_name: &str,
+  tool_name: &str,
+  args: Option<serde_json::Map<String, serde_json::Value>>,
+  call_id: &str,
+  timeout_ms: Option<u64>,
+) -> Result<serde_json::Value, String> {
+  let service = state.peer(server_name)?;
+  let args = arguments::check(state, server_name, tool_name, args).await?;
   let param = CallToolRequestParam {
-    name: tool_name.clone().into(),
+    name: tool_name.to_string().into(),
     arguments: args,
   };
 
-  let res = match service.call_tool(param).await {
+  let (token, cancel_rx) = calls::register(state, call_id, server_name, tool_name)?;
+  let outcome = run_tool(
+    app,
+    state,
+    &service,
+    server_name,
+    tool_name,
+    param,
+    call_id,
+    timeout_ms,
+    cancel_rx,
+  )
+  .await;
+  calls::unregister(state, call_id, token);
+  outcome
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_tool(
+  app: &AppHandle,
+  state: &McpState,
+  service: &Peer<RoleClient>,
+  server_name: &str,
+  tool_name: &str,
+  param: CallToolRequestParam,
+  call_id: &str,
+  timeout_ms: Option<u64>,
+  mut cancel_rx: oneshot::Receiver<String>,
+) -> Result<serde_json::Value, String> {
+  let limiter = state
+    .servers
+    .get(server_name)
+    .map(|e| e.limiter.clone())
+    .unwrap_or_default();
+  let acquire = limiter.acquire(server_name, |estimated| {
+    log::debug!(
+      "[MCP] Tool call {} queued by limits of server {}",
+      call_id,
+      server_name
+    );
+    let _ = app.emit(
+      calls::CALL_QUEUED_EVENT,
+      serde_json::json!({
+        "callId": call_id,
+        "serverName": server_name,
+        "toolName": tool_name,
+        "estimatedWaitMs": estimated.map(|d| d.as_millis() as u64),
+      }),
+    );
+  });
+  // 排队期间同样响应取消；若已为速率限制预订了令牌，该令牌不会退还
+  let permit = tokio::select! {
+    permit = acquire => permit.inspect_err(|e| log::warn!("[MCP] {}", e))?,
+    reason = &mut cancel_rx => {
+      let reason = reason.unwrap_or_else(|_| "cancelled".to_string());
+      log::info!("[MCP] Tool call {} cancelled while queued: {}", call_id, reason);
+      return Err(format!("Tool call cancelled: {}", reason));
+    }
+  };
+
+  let _ = app.emit(
+    calls::CALL_STARTED_EVENT,
+    serde_json::json!({
+      "callId": call_id,
+      "serverName": server_name,
+      "toolName": tool_name,
+      "waitedMs": permit.waited.as_millis() as u64,
+    }),
+  );
+
+  let res = match calls::call_tool_cancellable(
+    state,
+    service,
+    call_id,
+    server_name,
+    param,
+    timeout_ms.map(Duration::from_millis),
+    cancel_rx,
+  )
+  .await
+  {
     Ok(result) => {
       log::debug!(
         "[MCP] Tool {} called successfully on server {}",
@@ -782,35 +404,433 @@ pub async fn mcp_call_tool(
         server_name,
         e
       );
-      return Err(e.to_string());
+      return Err(e);
     }
   };
 
-  match serde_json::to_value(res) {
+  match results::process(app, state, server_name, tool_name, res).await {
     Ok(value) => Ok(value),
     Err(e) => {
-      log::error!("[MCP] Failed to serialize tool result: {}", e);
-      Err(e.to_string())
+      log::error!("[MCP] Failed to process tool result: {}", e);
+      Err(e)
+    }
+  }
+}
+
+/// 汇总所有已连接服务器的工具，每个工具带有全局唯一的限定名（`<服务器前缀>__<工具名>`），
+/// 已禁用的服务器与被过滤的工具不会出现
+#[tauri::command]
+pub async fn mcp_list_all_tools(
+  app: AppHandle,
+  force_refresh: Option<bool>,
+  state: State<'_, McpState>,
+) -> Result<Vec<McpQualifiedTool>, String> {
+  let tools = registry::list_all(&app, &state, force_refresh.unwrap_or(false)).await;
+  log::debug!("[MCP] Aggregated {} tools", tools.len());
+  Ok(tools)
+}
+
+/// 按限定名调用工具（由 `mcp_list_all_tools` 返回），自动路由到对应服务器；
+/// 其余参数与 `mcp_call_tool` 相同
+#[tauri::command]
+pub async fn mcp_call_qualified_tool(
+  app: AppHandle,
+  qualified_name: String,
+  args: Option<serde_json::Map<String, serde_json::Value>>,
+  call_id: Option<String>,
+  timeout_ms: Option<u64>,
+  confirmed: Option<bool>,
+  state: State<'_, McpState>,
+) -> Result<serde_json::Value, String> {
+  let (server_name, tool_name) = registry::resolve(&state, &qualified_name).await?;
+  call_tool(
+    &app,
+    &state,
+    server_name,
+    tool_name,
+    args,
+    call_id,
+    timeout_ms,
+    confirmed.unwrap_or(false),
+  )
+  .await
+}
+
+/// 读取各服务器的工具过滤设置（未设置的服务器按默认：启用且不过滤）
+#[tauri::command]
+pub async fn mcp_get_tool_filters(
+  app: AppHandle,
+) -> Result<HashMap<String, McpToolFilter>, String> {
+  Ok(registry::load_filters(&app))
+}
+
+/// 更新某服务器的工具过滤设置（启用/禁用、allow/deny 模式）
+#[tauri::command]
+pub async fn mcp_set_tool_filter(
+  app: AppHandle,
+  server_name: String,
+  filter: McpToolFilter,
+) -> Result<(), String> {
+  registry::save_filter(&app, &server_name, &filter)?;
+  log::info!(
+    "[MCP/registry] Updated tool filter for server {} (enabled={})",
+    server_name,
+    filter.enabled
+  );
+  Ok(())
+}
+
+/// 读取某服务器的工具调用策略（未设置时默认全部直接调用）
+#[tauri::command]
+pub async fn mcp_get_tool_policies(
+  app: AppHandle,
+  server_name: String,
+) -> Result<McpServerToolPolicies, String> {
+  Ok(tool_policy::load(&app, &server_name))
+}
+
+/// 设置工具调用策略：`tool_name` 为空时设置该服务器的默认策略
+#[tauri::command]
+pub async fn mcp_set_tool_policy(
+  app: AppHandle,
+  server_name: String,
+  tool_name: Option<String>,
+  policy: ToolPolicy,
+) -> Result<(), String> {
+  let mut policies = tool_policy::load(&app, &server_name);
+  match tool_name.as_deref().filter(|s| !s.is_empty()) {
+    Some(tool_name) => {
+      policies.tools.insert(tool_name.to_string(), policy);
+    }
+    None => policies.default = policy,
+  }
+  tool_policy::save(&app, &server_name, &policies)?;
+  log::info!(
+    "[MCP/tool-policy] Set {:?} for server {} tool {}",
+    policy,
+    server_name,
+    tool_name.as_deref().unwrap_or("*")
+  );
+  Ok(())
+}
+
+/// 查询工具调用审计日志（最新的在前）
+#[tauri::command]
+pub async fn mcp_query_audit_log(
+  app: AppHandle,
+  query: Option<McpAuditQuery>,
+  state: State<'_, McpState>,
+) -> Result<Vec<McpAuditEntry>, String> {
+  audit::query(&app, &state, query.unwrap_or_default()).await
+}
+
+/// 分段读取被截断的工具结果文本（handle 与 nextOffset 来自结果中的 `truncated` 字段）
+#[tauri::command]
+pub async fn mcp_read_result_text(
+  app: AppHandle,
+  handle: String,
+  offset: usize,
+  limit: Option<usize>,
+) -> Result<McpTextChunk, String> {
+  results::read_text(&app, &handle, offset, limit).await
+}
+
+/// 取消一个进行中的工具调用（向服务器发送取消通知并丢弃等待中的结果）
+#[tauri::command]
+pub async fn mcp_cancel_call(
+  call_id: String,
+  reason: Option<String>,
+  state: State<'_, McpState>,
+) -> Result<bool, String> {
+  match state.pending_calls.remove(&call_id) {
+    Some((_, pending)) => {
+      log::info!(
+        "[MCP] Cancelling tool call {} ({} on {})",
+        call_id,
+        pending.tool_name,
+        pending.server_name
+      );
+      pending.cancel(reason.unwrap_or_else(|| "cancelled by user".to_string()));
+      Ok(true)
+    }
+    None => {
+      log::debug!("[MCP] Tool call {} not pending, nothing to cancel", call_id);
+      Ok(false)
+    }
+  }
+}
+
+/// 回填服务器发起的采样请求（`mcp-sampling-request` 事件）的处理结果
+#[tauri::command]
+pub async fn mcp_respond_sampling(
+  request_id: String,
+  response: McpSamplingResponse,
+  state: State<'_, McpState>,
+) -> Result<(), String> {
+  log::debug!(
+    "[MCP/sampling] Response for {}: approved={}",
+    request_id,
+    response.approved
+  );
+  if state.sampling.resolve(&request_id, response) {
+    Ok(())
+  } else {
+    Err(format!(
+      "Sampling request {} not found (expired or cancelled)",
+      request_id
+    ))
+  }
+}
+
+/// 回填服务器发起的 elicitation 请求（`mcp-elicitation-request` 事件）。
+/// accept 的内容不满足 requestedSchema 时返回错误且请求保持等待，前端可修正后重新提交。
+#[tauri::command]
+pub async fn mcp_respond_elicitation(
+  request_id: String,
+  response: McpElicitationResponse,
+  state: State<'_, McpState>,
+) -> Result<(), String> {
+  let requested_schema = state.elicitations.context(&request_id).ok_or_else(|| {
+    format!(
+      "Elicitation request {} not found (expired or cancelled)",
+      request_id
+    )
+  })?;
+  if let Err(errors) = elicitation::check_reply(&requested_schema, &response) {
+    let msg = format!("Invalid input: {}", schema::describe(&errors));
+    log::warn!("[MCP/elicitation] {} rejected: {}", request_id, msg);
+    return Err(msg);
+  }
+  if state.elicitations.resolve(&request_id, response) {
+    Ok(())
+  } else {
+    Err(format!(
+      "Elicitation request {} not found (expired or cancelled)",
+      request_id
+    ))
+  }
+}
+
+/// 回填内置 MCP 服务器转交的知识库检索（`mcp-local-kb-query` 事件）
+#[tauri::command]
+pub async fn mcp_respond_kb_query(
+  request_id: String,
+  response: McpKbQueryResponse,
+  state: State<'_, McpState>,
+) -> Result<(), String> {
+  if state.kb_queries.resolve(&request_id, response) {
+    Ok(())
+  } else {
+    Err(format!(
+      "Knowledge base query {} not found (expired or cancelled)",
+      request_id
+    ))
+  }
+}
+
+/// 按配置逐步诊断连接问题（环境、路径、网络、握手、协议版本与能力），返回分步报告。
+/// 会建立一次临时连接并在完成后断开，不影响已连接的同名服务器。
+/// 与 `mcp_connect` 相同，需要确认的 stdio 命令须在用户确认后带 `confirmed` 重新调用
+#[tauri::command]
+pub async fn mcp_diagnose(
+  app: AppHandle,
+  server_name: String,
+  config: McpServerConfig,
+  confirmed: Option<bool>,
+) -> Result<McpDiagnosticReport, String> {
+  log::info!("[MCP/diagnose] Diagnosing server {}", server_name);
+  let report = diagnose::run(&app, &server_name, &config, confirmed.unwrap_or(false)).await;
+  log::info!(
+    "[MCP/diagnose] Server {} diagnosis finished (ok={})",
+    server_name,
+    report.ok
+  );
+  Ok(report)
+}
+
+/// 读取 stdio 服务器最近的 stderr 输出（默认最多 200 行）
+#[tauri::command]
+pub async fn mcp_get_server_logs(
+  server_name: String,
+  limit: Option<usize>,
+  state: State<'_, McpState>,
+) -> Result<Vec<String>, String> {
+  Ok(
+    state
+      .stderr_logs
+      .get(&server_name)
+      .map(|l| l.recent(limit.unwrap_or(200)))
+      .unwrap_or_default(),
+  )
+}
+
+// —— 密钥（供配置中的 ${secret:NAME} 引用） ——
+#[tauri::command]
+pub async fn mcp_set_secret(app: AppHandle, name: String, value: String) -> Result<(), String> {
+  if name.trim().is_empty() || name.contains('}') {
+    return Err(format!("Invalid secret name: {}", name));
+  }
+  expand::set_secret(&app, &name, &value)
+}
+
+#[tauri::command]
+pub async fn mcp_delete_secret(app: AppHandle, name: String) -> Result<(), String> {
+  expand::delete_secret(&app, &name)
+}
+
+/// 列出已保存的密钥名（不返回值）
+#[tauri::command]
+pub async fn mcp_list_secrets(app: AppHandle) -> Result<Vec<String>, String> {
+  expand::list_secrets(&app)
+}
+
+// —— 从其它客户端导入 ——
+/// 扫描 Claude Desktop / Cursor / VS Code / Windsurf 的配置（以及额外文件或粘贴的 JSON），
+/// 返回转换后的预览，标出不符合 stdio 策略的条目；实际导入由前端确认后保存
+#[tauri::command]
+pub async fn mcp_import_preview(
+  app: AppHandle,
+  extra_paths: Option<Vec<String>>,
+  text: Option<String>,
+) -> Result<McpImportPreview, String> {
+  let loaded = policy::load(&app)?;
+  let preview = import::preview(
+    &extra_paths.unwrap_or_default(),
+    text.as_deref(),
+    &loaded.policy,
+  );
+  log::info!(
+    "[MCP/import] Found {} servers in {} sources",
+    preview.servers.len(),
+    preview.sources.iter().filter(|s| s.found).count()
+  );
+  Ok(preview)
+}
+
+// —— stdio 策略 ——
+/// 读取当前生效的 stdio 策略及其来源
+#[tauri::command]
+pub async fn mcp_get_policy(app: AppHandle) -> Result<LoadedPolicy, String> {
+  policy::load(&app)
+}
+
+/// 保存用户级策略；存在企业策略时拒绝修改
+#[tauri::command]
+pub async fn mcp_save_policy(app: AppHandle, policy: StdioPolicy) -> Result<(), String> {
+  policy::save_user_policy(&app, &policy)?;
+  log::info!("[MCP/policy] User policy saved");
+  Ok(())
+}
+
+/// 连接前预检：返回是否允许、是否需要确认以及全部拒绝原因。
+/// 与连接时一致，在展开 ${VAR} / ${secret:NAME} 后的配置上评估
+#[tauri::command]
+pub async fn mcp_check_policy(
+  app: AppHandle,
+  config: McpServerConfig,
+) -> Result<PolicyDecision, String> {
+  let resolved = expand::resolve_config(&app, &config)?;
+  Ok(policy::check(&app, &resolved, &config.args))
+}
+
+// —— OAuth ——
+/// 对远程（sse/http）服务器执行 OAuth 授权：打开浏览器，完成后保存令牌。
+/// 授权成功后需重新连接，新令牌才会用于传输。
+#[tauri::command]
+pub async fn mcp_authorize(
+  app: AppHandle,
+  name: String,
+  config: McpServerConfig,
+) -> Result<McpAuthStatus, String> {
+  if !matches!(config.r#type.as_str(), "sse" | "http" | "auto") {
+    return Err(format!(
+      "Authorization is only supported for remote servers, got {}",
+      config.r#type
+    ));
+  }
+  // 与连接时一致先展开 ${VAR} / ${secret:NAME}，保存的 resource 才能与连接时的 baseUrl 匹配
+  let config = expand::resolve_config(&app, &config)?;
+  let base = config
+    .base_url
+    .clone()
+    .ok_or_else(|| "baseUrl required for authorization".to_string())?;
+  let client = remote_client(&config, &base, "auth", Vec::new())?;
+  let stored = auth::authorize(&app, &client, &name, &base)
+    .await
+    .map_err(|e| {
+      log::error!("[MCP/auth] Authorization failed for {}: {}", name, e);
+      e
+    })?;
+  Ok(McpAuthStatus::of(&name, Some(&stored)))
+}
+
+#[tauri::command]
+pub async fn mcp_get_auth_status(app: AppHandle, name: String) -> Result<McpAuthStatus, String> {
+  Ok(McpAuthStatus::of(
+    &name,
+    auth::load_auth(&app, &name).as_ref(),
+  ))
+}
+
+/// 删除已保存的令牌与客户端注册信息
+#[tauri::command]
+pub async fn mcp_clear_auth(app: AppHandle, name: String) -> Result<(), String> {
+  auth::clear_auth(&app, &name)
+}
+
+// —— Roots ——
+/// 查询某服务器已授权的工作目录
+#[tauri::command]
+pub async fn mcp_get_roots(app: AppHandle, server_name: String) -> Result<Vec<Root>, String> {
+  Ok(roots::load_roots(&app, &server_name))
+}
+
+/// 更新某服务器已授权的工作目录；若服务器已连接则发送 `roots/list_changed` 通知
+#[tauri::command]
+pub async fn mcp_set_roots(
+  app: AppHandle,
+  server_name: String,
+  roots: Vec<McpRootInput>,
+  state: State<'_, McpState>,
+) -> Result<Vec<Root>, String> {
+  let normalized = roots
+    .into_iter()
+    .map(|r| roots::normalize_root(&r.path, r.name))
+    .collect::<Result<Vec<Root>, String>>()?;
+  roots::save_roots(&app, &server_name, &normalized)?;
+  log::info!(
+    "[MCP/roots] Saved {} roots for server {}",
+    normalized.len(),
+    server_name
+  );
+
+  if let Ok(peer) = state.peer(&server_name) {
+    if let Err(e) = peer.notify_roots_list_changed().await {
+      log::warn!(
+        "[MCP/roots] Failed to notify roots change to server {}: {}",
+        server_name,
+        e
+      );
     }
   }
+  Ok(normalized)
 }
 
 // —— Resources ——
 #[tauri::command]
 pub async fn mcp_list_resources(
   server_name: String,
+  force_refresh: Option<bool>,
   state: State<'_, McpState>,
 ) -> Result<serde_json::Value, String> {
   log::debug!("[MCP] Listing resources for server: {}", server_name);
 
-  let service = state
-    .0
-    .get(&server_name)
-    .ok_or_else(|| "Server not found".to_string())?;
-  let res = match service.list_resources(Default::default()).await {
+  let resources = match cache::resources(&state, &server_name, force_refresh.unwrap_or(false)).await
+  {
     Ok(result) => {
       log::debug!(
-        "[MCP] Successfully listed resources from server {}",
+        "[MCP] Successfully listed {} resources from server {}",
+        result.len(),
         server_name
       );
       result
@@ -821,17 +841,12 @@ pub async fn mcp_list_resources(
         server_name,
         e
       );
-      return Err(e.to_string());
+      return Err(e);
     }
   };
 
-  match serde_json::to_value(res) {
-    Ok(value) => Ok(value),
-    Err(e) => {
-      log::error!("[MCP] Failed to serialize resources result: {}", e);
-      Err(e.to_string())
-    }
-  }
+  // 保持与 ListResourcesResult 一致的结构（已合并全部分页，故无 nextCursor）
+  Ok(serde_json::json!({ "resources": resources }))
 }
 
 #[tauri::command]
@@ -846,10 +861,7 @@ pub async fn mcp_read_resource(
     server_name
   );
 
-  let service = state
-    .0
-    .get(&server_name)
-    .ok_or_else(|| "Server not found".to_string())?;
+  let service = state.peer(&server_name)?;
   let params = ReadResourceRequestParam {
     uri: uri.clone().into(),
   };
@@ -883,22 +895,117 @@ pub async fn mcp_read_resource(
   }
 }
 
+/// 列出资源模板（自动跟随分页）
+#[tauri::command]
+pub async fn mcp_list_resource_templates(
+  server_name: String,
+  state: State<'_, McpState>,
+) -> Result<Vec<ResourceTemplate>, String> {
+  log::debug!(
+    "[MCP] Listing resource templates for server: {}",
+    server_name
+  );
+
+  let service = state.peer(&server_name)?;
+  match cache::fetch_all_resource_templates(&service).await {
+    Ok(templates) => {
+      log::debug!(
+        "[MCP] Successfully listed {} resource templates from server {}",
+        templates.len(),
+        server_name
+      );
+      Ok(templates)
+    }
+    Err(e) => {
+      log::error!(
+        "[MCP] Failed to list resource templates from server {}: {}",
+        server_name,
+        e
+      );
+      Err(e.to_string())
+    }
+  }
+}
+
+/// 订阅资源变更；服务器推送 `resources/updated` 时前端会收到 `mcp-resource-updated` 事件
+#[tauri::command]
+pub async fn mcp_subscribe_resource(
+  server_name: String,
+  uri: String,
+  state: State<'_, McpState>,
+) -> Result<(), String> {
+  log::debug!(
+    "[MCP] Subscribing resource {} on server {}",
+    uri,
+    server_name
+  );
+
+  let service = state.peer(&server_name)?;
+  if let Err(e) = service
+    .subscribe(SubscribeRequestParam { uri: uri.clone() })
+    .await
+  {
+    log::error!(
+      "[MCP] Failed to subscribe resource {} on server {}: {}",
+      uri,
+      server_name,
+      e
+    );
+    return Err(e.to_string());
+  }
+  state
+    .subscriptions
+    .entry(server_name)
+    .or_default()
+    .insert(uri);
+  Ok(())
+}
+
+#[tauri::command]
+pub async fn mcp_unsubscribe_resource(
+  server_name: String,
+  uri: String,
+  state: State<'_, McpState>,
+) -> Result<(), String> {
+  log::debug!(
+    "[MCP] Unsubscribing resource {} on server {}",
+    uri,
+    server_name
+  );
+
+  if let Some(mut subs) = state.subscriptions.get_mut(&server_name) {
+    subs.remove(&uri);
+  }
+  let service = state.peer(&server_name)?;
+  if let Err(e) = service
+    .unsubscribe(UnsubscribeRequestParam { uri: uri.clone() })
+    .await
+  {
+    log::error!(
+      "[MCP] Failed to unsubscribe resource {} on server {}: {}",
+      uri,
+      server_name,
+      e
+    );
+    return Err(e.to_string());
+  }
+  Ok(())
+}
+
 // —— Prompts ——
 #[tauri::command]
 pub async fn mcp_list_prompts(
   server_name: String,
+  force_refresh: Option<bool>,
   state: State<'_, McpState>,
 ) -> Result<serde_json::Value, String> {
   log::debug!("[MCP] Listing prompts for server: {}", server_name);
 
-  let service = state
-    .0
-    .get(&server_name)
-    .ok_or_else(|| "Server not found".to_string())?;
-  let res = match service.list_prompts(Default::default()).await {
+  let prompts = match cache::prompts(&state, &server_name, force_refresh.unwrap_or(false)).await {
     Ok(result) => {
       log::debug!(
-        "[MCP] Successfully listed prompts from server {}",
+        "[MCP] Successfully listed {} prompts from server {}",
+        result.len(),
         server_name
       );
       result
@@ -909,17 +1016,12 @@ pub async fn mcp_list_prompts(
         server_name,
         e
       );
-      return Err(e.to_string());
+      return Err(e);
     }
   };
 
-  match serde_json::to_value(res) {
-    Ok(value) => Ok(value),
-    Err(e) => {
-      log::error!("[MCP] Failed to serialize prompts result: {}", e);
-      Err(e.to_string())
-    }
-  }
+  // 保持与 ListPromptsResult 一致的结构（已合并全部分页，故无 nextCursor）
+  Ok(serde_json::json!({ "prompts": prompts }))
 }
 
 #[tauri::command]
@@ -936,10 +1038,7 @@ pub async fn mcp_get_prompt(
     args
   );
 
-  let service = state
-    .0
-    .get(&server_name)
-    .ok_or_else(|| "Server not found".to_string())?;
+  let service = state.peer(&server_name)?;
   let params = GetPromptRequestParam {
     name: name.clone().into(),
     arguments: args,
@@ -0,0 +1,826 @@
+use crate::env_setup::EnvironmentSetup;
+use crate::mcp::auth;
+use crate::mcp::expand;
+use crate::mcp::handler::McpClientHandler;
+use crate::mcp::policy;
+use crate::mcp::sandbox;
+use crate::mcp::state::{McpService, McpState};
+use crate::mcp::stderr;
+use crate::mcp::types::McpServerConfig;
+use reqwest::Url;
+use rmcp::{
+  model::ProtocolVersion,
+  service::ServiceExt,
+  transport::{
+    sse_client::{SseClientConfig, SseClientTransport},
+    streamable_http_client::{StreamableHttpClientTransport, StreamableHttpClientTransportConfig},
+    ConfigureCommandExt, TokioChildProcess,
+  },
+};
+use std::net::IpAddr;
+use std::process::Stdio;
+use tauri::{AppHandle, Manager};
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+// 仅在Windows平台编译时，引入 CommandExt Trait
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+// —— 工具：从 npx 参数中提取第一个包名（用于首次安装的预拉取） ——
+fn extract_npx_package(args: &Option<Vec<String>>) -> Option<String> {
+  if let Some(a) = args {
+    for it in a {
+      // 跳过常见的 flags（以 - 开头）
+      if it.starts_with('-') {
+        continue;
+      }
+      return Some(it.clone());
+    }
+  }
+  None
+}
+
+async fn npx_prefetch_package(pkg: &str) -> Result<(), String> {
+  // 只下载依赖，不启动 MCP；最长等待 4 分钟以适配首次安装
+  let mut pre = Command::new("npx");
+
+  // [关键步骤] 为Windows平台设置无窗口创建标志
+  #[cfg(windows)]
+  {
+    pre.creation_flags(0x08000000); // CREATE_NO_WINDOW
+  }
+
+  pre.env("NPM_CONFIG_LOGLEVEL", "silent");
+  pre.env("NO_COLOR", "1");
+  pre.env("NPX_Y", "1");
+  pre.args(["-y", "-p", pkg, "node", "-e", "process.exit(0)"]);
+  log::info!("[MCP/npx] prefetch package: {}", pkg);
+  let status = timeout(Duration::from_secs(240), pre.status())
+    .await
+    .map_err(|_| "npx prefetch timeout".to_string())?
+    .map_err(|e| e.to_string())?;
+  if !status.success() {
+    return Err(format!("npx prefetch failed with code {:?}", status.code()));
+  }
+  Ok(())
+}
+
+fn is_path_like(arg: &str) -> bool {
+  if arg.is_empty() {
+    return false;
+  }
+  let lower = arg.to_lowercase();
+  if lower.starts_with("http://") || lower.starts_with("https://") {
+    return false;
+  }
+  // npm scope 包名，如 @scope/name，不应被当作路径
+  if arg.starts_with('@') {
+    // 典型包名不包含反斜杠和盘符
+    if arg.contains('/') && !arg.contains('\\') && !arg.contains(':') {
+      return false;
+    }
+  }
+  if arg.starts_with('/')
+    || arg.starts_with("./")
+    || arg.starts_with("../")
+    || arg.starts_with("~/")
+  {
+    return true;
+  }
+  // Windows 盘符，如 C:\ 或 C:/
+  if arg.len() >= 3 {
+    let bytes = arg.as_bytes();
+    if bytes[1] == b':' && (bytes[2] == b'/' || bytes[2] == b'\\') {
+      return true;
+    }
+  }
+  // 包含路径分隔符的其它情况
+  arg.contains('/') || arg.contains('\\')
+}
+
+/// 找出看起来像路径但不存在的参数。
+/// 规则：从第一个非 flag 参数（一般是包名）之后的参数中筛选；
+/// Windows 包装器 cmd /c <cmd> <args...> 需要跳过前两个参数。
+/// `args` 为 ${VAR} / ~ 展开后的参数，用于判断与检查路径；相对路径按子进程的工作目录解析。
+/// 日志与返回值只使用对应的原始参数 `raw_args`，引用密钥的参数不参与检查，避免泄露密钥
+pub(crate) async fn missing_path_args(
+  cmd_name: &str,
+  args: &[String],
+  raw_args: &[String],
+  cwd: Option<&str>,
+) -> Vec<String> {
+  let wrapper_offset = if cmd_name.eq_ignore_ascii_case("cmd")
+    && args
+      .first()
+      .map(|s| s.eq_ignore_ascii_case("/c"))
+      .unwrap_or(false)
+    && args.len() >= 2
+  {
+    2
+  } else {
+    0
+  };
+  log::debug!("[MCP/stdio] Wrapper offset: {}", wrapper_offset);
+
+  let args_slice = &args[wrapper_offset..];
+  let Some(mut idx) = args_slice.iter().position(|it| !it.starts_with('-')) else {
+    return Vec::new();
+  };
+  log::debug!("[MCP/stdio] First non-flag arg index: {}", idx);
+
+  // 特判：cmd /c + npx/uvx/bunx 的形式。此时 idx 指向的是执行器（npx），
+  // 需要继续向后找到真正的包名（第一个非 flag），并从包名之后开始校验路径。
+  let is_runner = |s: &str| {
+    s.eq_ignore_ascii_case("npx") || s.eq_ignore_ascii_case("uvx") || s.eq_ignore_ascii_case("bunx")
+  };
+  if args_slice.get(idx).map(|s| is_runner(s)).unwrap_or(false) {
+    if let Some(pidx) = args_slice
+      .iter()
+      .enumerate()
+      .skip(idx + 1)
+      .find(|(_, it)| !it.starts_with('-'))
+      .map(|(j, _)| j)
+    {
+      idx = pidx;
+      log::debug!("[MCP/stdio] Adjusted package index to: {}", idx);
+    }
+  }
+
+  let start = wrapper_offset + idx + 1; // 从包名后的参数开始
+  log::debug!("[MCP/stdio] Path validation start index: {}", start);
+
+  let mut missing: Vec<String> = Vec::new();
+  for (i, arg) in args.iter().enumerate().skip(start) {
+    let raw = raw_args.get(i).unwrap_or(arg);
+    if raw.contains("${secret:") || !is_path_like(arg) {
+      continue;
+    }
+    log::debug!("[MCP/stdio] Checking path-like argument: {}", raw);
+    let target = match cwd {
+      Some(cwd) if std::path::Path::new(arg).is_relative() => std::path::Path::new(cwd).join(arg),
+      _ => std::path::PathBuf::from(arg),
+    };
+    match tokio::fs::metadata(&target).await {
+      Ok(metadata) => {
+        log::debug!(
+          "[MCP/stdio] Path exists: {} (type: {:?})",
+          raw,
+          metadata.file_type()
+        );
+      }
+      Err(e) => {
+        log::warn!("[MCP/stdio] Path does not exist: {} (error: {})", raw, e);
+        missing.push(raw.clone());
+      }
+    }
+  }
+  missing
+}
+
+pub(crate) fn missing_paths_message(missing: &[String]) -> String {
+  let mut msg = format!("Path arguments do not exist: {}", missing.join(", "));
+  let placeholder_hit = missing.iter().any(|s| {
+    let ls = s.to_lowercase();
+    ls.contains("/users/username/") || ls.contains("path/to/other/allowed/dir")
+  });
+  if placeholder_hit {
+    msg.push_str(". It looks like placeholder paths are still present. Please replace them with real existing directories.");
+  }
+  msg
+}
+
+/// 按配置建立一次 MCP 连接（不写入状态），供 `mcp_connect` 与断线重连共用。
+/// 返回服务及实际使用的传输方式（type 为 "auto" 时由探测决定）。
+/// `confirmed` 表示用户已确认启动该 stdio 命令；策略要求确认而未确认时返回 `MCP_CONFIRMATION_REQUIRED:` 错误
+pub(crate) async fn connect_server(
+  app: &AppHandle,
+  name: &str,
+  config: &McpServerConfig,
+  confirmed: bool,
+) -> Result<(McpService, &'static str), String> {
+  connect_session(app, name, name, config, confirmed).await
+}
+
+/// 同 `connect_server`，但 stderr 缓冲、子进程记录与客户端事件登记在 `session` 名下；
+/// OAuth 凭据、沙箱目录等仍按 `name` 查找。诊断用它建立不干扰同名服务器的临时连接
+pub(crate) async fn connect_session(
+  app: &AppHandle,
+  name: &str,
+  session: &str,
+  config: &McpServerConfig,
+  confirmed: bool,
+) -> Result<(McpService, &'static str), String> {
+  // 展开 ${VAR} / ~ / ${secret:NAME}；日志只打印原始参数，避免泄露密钥
+  let raw_args = config.args.clone();
+  let resolved = expand::resolve_config(app, config)?;
+  let config = &resolved;
+
+  let transport = match config.r#type.as_str() {
+    "stdio" => "stdio",
+    "sse" => "sse",
+    "http" => "http",
+    "auto" => {
+      let base = config
+        .base_url
+        .clone()
+        .ok_or_else(|| "baseUrl required for auto".to_string())?;
+      let client = authorized_client(app, name, config, &base, "auto").await?;
+      detect_transport(&client, &base).await?
+    }
+    other => {
+      let error_msg = format!("Unsupported transport type: {}", other);
+      log::error!("[MCP] {}", error_msg);
+      return Err(error_msg);
+    }
+  };
+
+  let service = connect_transport(app, name, session, config, transport, &raw_args, confirmed)
+    .await
+    .inspect_err(|_| {
+      // 启动失败的子进程已随传输结束，不保留其 pid，避免退出时向可能已被复用的进程组发送信号
+      app.state::<McpState>().child_pids.remove(session);
+    })?;
+  Ok((service, transport))
+}
+
+/// 规范中的向后兼容探测：先以可流式 HTTP 方式 POST initialize，
+/// 成功则使用 http；返回 4xx（401/403 除外）则回退到旧版 SSE（GET 端点）。
+async fn detect_transport(client: &reqwest::Client, base: &str) -> Result<&'static str, String> {
+  let body = serde_json::json!({
+    "jsonrpc": "2.0",
+    "id": 0,
+    "method": "initialize",
+    "params": {
+      "protocolVersion": ProtocolVersion::LATEST,
+      "capabilities": {},
+      "clientInfo": { "name": "chatless", "version": env!("CARGO_PKG_VERSION") }
+    }
+  });
+  let resp = client
+    .post(base)
+    .header(
+      reqwest::header::ACCEPT,
+      "application/json, text/event-stream",
+    )
+    .json(&body)
+    .timeout(Duration::from_secs(15))
+    .send()
+    .await
+    .map_err(|e| format!("Transport probe failed: {}", e))?;
+  let status = resp.status();
+
+  if status.is_success() {
+    // 探测建立的会话不再使用，礼貌地结束它
+    if let Some(session) = resp.headers().get("mcp-session-id").cloned() {
+      let _ = client
+        .delete(base)
+        .header("mcp-session-id", session)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await;
+    }
+    log::info!("[MCP/auto] {} supports streamable HTTP", base);
+    return Ok("http");
+  }
+  if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+    return Err(with_auth_hint(format!(
+      "Transport probe failed: HTTP {}",
+      status
+    )));
+  }
+  if status.is_client_error() {
+    log::info!(
+      "[MCP/auto] {} answered POST with {}, falling back to SSE",
+      base,
+      status
+    );
+    return Ok("sse");
+  }
+  Err(format!("Transport probe failed: HTTP {}", status))
+}
+
+async fn connect_transport(
+  app: &AppHandle,
+  name: &str,
+  session: &str,
+  config: &McpServerConfig,
+  transport: &str,
+  raw_args: &Option<Vec<String>>,
+  confirmed: bool,
+) -> Result<McpService, String> {
+  match transport {
+    "stdio" => {
+      log::info!(
+        "[MCP/stdio] Initializing stdio connection for server: {}",
+        name
+      );
+
+      let cmd_name = config
+        .command
+        .clone()
+        .ok_or_else(|| "command required for stdio".to_string())?;
+      log::info!("[MCP/stdio] Command name: {}", cmd_name);
+
+      // —— 基础安全校验：按 stdio 策略（可执行文件、参数模式、环境变量）检查 ——
+      let decision = policy::check(app, config, raw_args);
+      if !decision.allowed {
+        let error_msg = policy::denial_message(&decision);
+        log::error!("[MCP/stdio] Security check failed: {}", error_msg);
+        return Err(error_msg);
+      }
+      if decision.requires_confirmation && !confirmed {
+        return Err(format!(
+          "{}: starting '{}' requires confirmation",
+          policy::CONFIRMATION_REQUIRED,
+          cmd_name
+        ));
+      }
+
+      // 可选的沙箱（启用但不可用时直接报错）
+      let sandbox_plan = sandbox::plan(app, name, config)?;
+
+      // 构造命令的闭包，便于重试
+      let build_cmd = || {
+        log::debug!("[MCP/stdio] Building command: {}", cmd_name);
+
+        let mut c = match &sandbox_plan {
+          Some(plan) => plan.command(&cmd_name),
+          None => Command::new(&cmd_name),
+        };
+
+        // 沙箱命令已清空环境，不再补回基础变量
+        if sandbox_plan.is_none() && !config.inherit_env.unwrap_or(true) {
+          c.env_clear();
+          for key in expand::BASELINE_ENV {
+            if let Some(v) = std::env::var_os(key) {
+              c.env(key, v);
+            }
+          }
+          log::debug!("[MCP/stdio] Environment inheritance disabled");
+        }
+
+        if let Some(cwd) = &config.cwd {
+          c.current_dir(cwd);
+          log::debug!("[MCP/stdio] Working directory: {}", cwd);
+        }
+
+        // [关键步骤] 为Windows平台设置无窗口创建标志
+        #[cfg(windows)]
+        {
+          c.creation_flags(0x08000000); // CREATE_NO_WINDOW
+          log::debug!("[MCP/stdio] Set Windows CREATE_NO_WINDOW flag");
+        }
+
+        // 独立进程组：退出时可按组结束 npx 等启动器派生的子进程
+        #[cfg(unix)]
+        c.process_group(0);
+
+        // 动态设置环境变量，特别是对于 npm 相关命令
+        if cmd_name == "npx" || cmd_name == "npm" || cmd_name == "node" {
+          log::debug!(
+            "[MCP/stdio] Setting up environment for {} command",
+            cmd_name
+          );
+
+          // 获取当前环境设置（用于调试）
+          let _current_path = std::env::var("PATH").unwrap_or_default();
+
+          // 如果检测到工具不可用，尝试重新设置环境
+          let env_checker = EnvironmentSetup::new();
+          if !env_checker.verify_tool_availability(&cmd_name) {
+            log::warn!(
+              "[MCP/stdio] Tool {} not found in current PATH, attempting to refresh environment",
+              cmd_name
+            );
+
+            // 尝试设置环境变量
+            if let Ok(()) = EnvironmentSetup::new().setup() {
+              // 重新获取环境设置以获取更新后的 PATH
+              let updated_env = EnvironmentSetup::new();
+              let updated_path = updated_env.get_updated_path();
+              c.env("PATH", updated_path);
+              log::info!("[MCP/stdio] Updated PATH for {} command", cmd_name);
+            } else {
+              log::warn!(
+                "[MCP/stdio] Failed to setup environment for {} command",
+                cmd_name
+              );
+            }
+          } else {
+            log::debug!("[MCP/stdio] Tool {} is available in current PATH", cmd_name);
+          }
+        }
+
+        if let Some(args) = &config.args {
+          c.args(args);
+          log::debug!("[MCP/stdio] Added args: {:?}", raw_args);
+        }
+
+        if let Some(envs) = &config.env {
+          for (k, v) in envs {
+            c.env(k, v);
+            log::debug!("[MCP/stdio] Set env: {}", k);
+          }
+        }
+
+        if cmd_name == "npx" {
+          c.env("NPM_CONFIG_LOGLEVEL", "silent");
+          c.env("NO_COLOR", "1");
+          c.env("NPX_Y", "1");
+          log::debug!("[MCP/stdio] Set npx-specific environment variables");
+        }
+
+        // Log the final command for debugging
+        log::info!("[MCP/stdio] Built command: {} {:?}", cmd_name, raw_args);
+
+        c
+      };
+
+      // —— 参数校验，避免简单的 shell 注入字符 ——
+      if let Some(args) = &config.args {
+        log::debug!("[MCP/stdio] Validating arguments: {:?}", raw_args);
+
+        let joined = args.join(" ");
+        if joined.len() > 2048 {
+          let error_msg = "args too long".to_string();
+          log::error!("[MCP/stdio] Args validation failed: {}", error_msg);
+          return Err(error_msg);
+        }
+
+        if joined.contains('|')
+          || joined.contains('&')
+          || joined.contains(';')
+          || joined.contains('>')
+          || joined.contains('<')
+        {
+          let error_msg = "args contains forbidden shell characters".to_string();
+          log::error!("[MCP/stdio] Args validation failed: {}", error_msg);
+          return Err(error_msg);
+        }
+
+        // 通用路径存在性校验：检测看起来像路径的参数，如果不存在则直接提示（避免特定 MCP 魔法处理）
+        let raw = raw_args.as_deref().unwrap_or(args);
+        let missing = missing_path_args(&cmd_name, args, raw, config.cwd.as_deref()).await;
+        if !missing.is_empty() {
+          let msg = missing_paths_message(&missing);
+          log::error!("[MCP/stdio] Path validation failed: {}", msg);
+          return Err(msg);
+        }
+      }
+
+      // —— 调试日志 ——
+      log::info!("[MCP/stdio] Command validation passed, spawning process");
+      log::debug!(
+        "[MCP/stdio] Final command details: cmd='{}' args={:?} envs={}",
+        cmd_name,
+        raw_args,
+        config.env.as_ref().map(|v| v.len()).unwrap_or(0)
+      );
+
+      // 一次尝试的封装
+      let try_connect = || async {
+        log::debug!("[MCP/stdio] Attempting to spawn child process");
+        let cmd = build_cmd();
+
+        // Log current working directory and environment for debugging
+        if let Ok(current_dir) = std::env::current_dir() {
+          log::debug!("[MCP/stdio] Current working directory: {:?}", current_dir);
+        }
+
+        if let Ok(path) = std::env::var("PATH") {
+          log::debug!("[MCP/stdio] PATH environment: {}", path);
+        }
+
+        // 捕获 stderr，便于在启动失败时展示子进程的真实报错
+        stderr::begin_session(&app.state::<McpState>(), session);
+        match TokioChildProcess::builder(cmd.configure(|_c| {}))
+          .stderr(Stdio::piped())
+          .spawn()
+        {
+          Ok((process, child_stderr)) => {
+            log::debug!("[MCP/stdio] Child process created successfully");
+            if let Some(pid) = process.id() {
+              app
+                .state::<McpState>()
+                .child_pids
+                .insert(session.to_string(), pid);
+            }
+            if let Some(child_stderr) = child_stderr {
+              stderr::spawn_reader(app.clone(), session.to_string(), child_stderr);
+            }
+            let service: McpService = match McpClientHandler::new(app.clone(), session)
+              .serve(process)
+              .await
+            {
+              Ok(service) => service,
+              Err(e) => {
+                log::error!("[MCP/stdio] Service creation failed: {}", e);
+                return Err(stderr::with_tail(app, session, e.to_string()).await);
+              }
+            };
+            log::info!("[MCP/stdio] MCP service created successfully");
+            Ok::<McpService, String>(service)
+          }
+          Err(e) => {
+            log::error!("[MCP/stdio] Failed to create child process: {}", e);
+            Err(e.to_string())
+          }
+        }
+      };
+
+      // 第一次尝试（可能在 npx 首次下载时失败/超时）
+      log::info!("[MCP/stdio] Starting first connection attempt with 30s timeout");
+      let first = match timeout(Duration::from_secs(30), try_connect()).await {
+        Ok(r) => Ok(r),
+        Err(_) => {
+          log::error!("[MCP/stdio] First connection attempt timed out");
+          Err(stderr::with_tail(app, session, "Connect timeout (stdio)".to_string()).await)
+        }
+      };
+
+      match first {
+        Ok(Ok(service)) => {
+          log::info!(
+            "[MCP/stdio] First connection attempt successful for server: {}",
+            name
+          );
+          Ok(service)
+        }
+        Ok(Err(e)) | Err(e) => {
+          log::warn!("[MCP/stdio] First connection attempt failed: {}", e);
+
+          // 预下载在沙箱外执行（会运行包的安装脚本），启用沙箱时不做
+          if cmd_name == "npx" && sandbox_plan.is_some() {
+            log::error!(
+              "[MCP/stdio] Connection failed in sandbox, skipping unsandboxed npx prefetch"
+            );
+            Err(format!(
+              "{}. npx runs inside the sandbox; add its cache (e.g. ~/.npm) to sandbox.writablePaths so it can install the package",
+              e
+            ))
+          } else if cmd_name == "npx" {
+            if let Some(pkg) = extract_npx_package(&config.args) {
+              log::info!(
+                "[MCP/stdio] First connect failed: {}. prefetching {}...",
+                e,
+                pkg
+              );
+              // 预下载失败则直接返回组合错误
+              match npx_prefetch_package(&pkg).await {
+                Ok(()) => {
+                  log::info!("[MCP/stdio] Package prefetch successful, retrying connection");
+                }
+                Err(pe) => {
+                  log::error!("[MCP/stdio] Package prefetch failed: {}", pe);
+                  return Err(format!("{}; prefetch: {}", e, pe));
+                }
+              }
+
+              // 预下载成功后重试
+              log::info!("[MCP/stdio] Starting second connection attempt after prefetch");
+              let second = match timeout(Duration::from_secs(30), try_connect()).await {
+                Ok(r) => r?,
+                Err(_) => {
+                  log::error!("[MCP/stdio] Second connection attempt timed out");
+                  return Err(
+                    stderr::with_tail(
+                      app,
+                      session,
+                      "Connect timeout (stdio, after prefetch)".to_string(),
+                    )
+                    .await,
+                  );
+                }
+              };
+
+              log::info!(
+                "[MCP/stdio] Second connection attempt successful for server: {}",
+                name
+              );
+              Ok(second)
+            } else {
+              log::error!("[MCP/stdio] Failed to extract package name from npx args");
+              Err(e)
+            }
+          } else {
+            log::error!("[MCP/stdio] Connection failed and not using npx, cannot retry");
+            Err(e)
+          }
+        }
+      }
+    }
+    "sse" => {
+      log::info!("[MCP/sse] Initializing SSE connection for server: {}", name);
+
+      let base = config
+        .base_url
+        .clone()
+        .ok_or_else(|| "baseUrl required for sse".to_string())?;
+      log::info!("[MCP/sse] Connecting to baseUrl: {}", &base);
+
+      let req = authorized_client(app, name, config, &base, "sse").await?;
+      let cfg = SseClientConfig {
+        sse_endpoint: base.into(),
+        ..Default::default()
+      };
+      log::debug!("[MCP/sse] SSE config: {:?}", cfg);
+
+      let transport = match SseClientTransport::start_with_client(req, cfg).await {
+        Ok(transport) => {
+          log::debug!("[MCP/sse] SSE transport started successfully");
+          transport
+        }
+        Err(e) => {
+          log::error!("[MCP/sse] Failed to start SSE transport: {}", e);
+          return Err(with_auth_hint(e.to_string()));
+        }
+      };
+
+      let service: McpService = match McpClientHandler::new(app.clone(), session)
+        .serve(transport)
+        .await
+      {
+        Ok(service) => {
+          log::info!(
+            "[MCP/sse] SSE service created successfully for server: {}",
+            name
+          );
+          service
+        }
+        Err(e) => {
+          log::error!("[MCP/sse] Failed to create SSE service: {}", e);
+          return Err(with_auth_hint(e.to_string()));
+        }
+      };
+
+      log::info!(
+        "[MCP/sse] SSE connection established successfully for server: {}",
+        name
+      );
+      Ok(service)
+    }
+    "http" => {
+      log::info!(
+        "[MCP/http] Initializing HTTP connection for server: {}",
+        name
+      );
+
+      let base = config
+        .base_url
+        .clone()
+        .ok_or_else(|| "baseUrl required for http".to_string())?;
+      log::info!("[MCP/http] Connecting to baseUrl: {}", &base);
+
+      let req = authorized_client(app, name, config, &base, "http").await?;
+      let cfg = StreamableHttpClientTransportConfig::with_uri(base);
+      log::debug!("[MCP/http] HTTP transport config: {:?}", cfg);
+
+      let transport = StreamableHttpClientTransport::with_client(req, cfg);
+      log::debug!("[MCP/http] HTTP transport created successfully");
+
+      let service: McpService = match McpClientHandler::new(app.clone(), session)
+        .serve(transport)
+        .await
+      {
+        Ok(service) => {
+          log::info!(
+            "[MCP/http] HTTP service created successfully for server: {}",
+            name
+          );
+          service
+        }
+        Err(e) => {
+          log::error!("[MCP/http] Failed to create HTTP service: {}", e);
+          return Err(with_auth_hint(e.to_string()));
+        }
+      };
+
+      log::info!(
+        "[MCP/http] HTTP connection established successfully for server: {}",
+        name
+      );
+      Ok(service)
+    }
+    _ => {
+      let error_msg = format!("Unsupported transport type: {}", transport);
+      log::error!("[MCP] {}", error_msg);
+      Err(error_msg)
+    }
+  }
+}
+
+/// 为远程传输（sse/http）构建 HTTP 客户端。
+/// 代理选择策略：若 use_proxy=true 且 proxy_url 存在，且目标非本地/私网，则使用带代理客户端；
+/// 有额外请求头时使用附带这些头的自定义客户端；否则使用共享的浏览器化客户端。
+pub(crate) fn remote_client(
+  config: &McpServerConfig,
+  base: &str,
+  tag: &str,
+  extra_headers: Vec<(String, String)>,
+) -> Result<reqwest::Client, String> {
+  let should_use_proxy = config.use_proxy.unwrap_or(false)
+    && config.proxy_url.is_some()
+    && Url::parse(base)
+      .map(|u| !is_local_or_private(u.host_str().unwrap_or_default()))
+      // 无法解析 URL，保守起见不走代理
+      .unwrap_or(false);
+
+  if !should_use_proxy && extra_headers.is_empty() {
+    return match crate::http_client::get_browser_like_client() {
+      Ok(client) => {
+        log::debug!("[MCP/{}] Using browser-like HTTP client", tag);
+        Ok((*client).clone()) // 从Arc<Client>转换为Client
+      }
+      Err(e) => {
+        log::error!("[MCP/{}] Failed to get HTTP client: {}", tag, e);
+        Err(format!("Failed to get HTTP client: {}", e))
+      }
+    };
+  }
+
+  let mut cfg = crate::http_client::HttpClientConfig::default();
+  if should_use_proxy {
+    cfg.http1_only = true;
+    cfg.gzip = false;
+    cfg.brotli = false;
+    cfg.proxy_url = config.proxy_url.clone();
+  }
+  cfg.extra_headers = extra_headers;
+  match crate::http_client::HttpClientManager::build_custom_client(cfg) {
+    Ok(client) => {
+      log::debug!(
+        "[MCP/{}] Using custom HTTP client (proxy: {})",
+        tag,
+        should_use_proxy
+      );
+      Ok(client)
+    }
+    Err(e) => {
+      log::error!("[MCP/{}] Failed to build custom client: {}", tag, e);
+      Err(format!("Failed to build HTTP client: {}", e))
+    }
+  }
+}
+
+/// 在远程客户端上附加配置中的静态 headers 与已保存的 OAuth access token（必要时先刷新）
+async fn authorized_client(
+  app: &AppHandle,
+  name: &str,
+  config: &McpServerConfig,
+  base: &str,
+  tag: &str,
+) -> Result<reqwest::Client, String> {
+  let plain = remote_client(config, base, tag, Vec::new())?;
+  let mut headers = config.headers.clone().unwrap_or_default();
+  if let Some(token) = auth::access_token(app, &plain, name, base).await {
+    log::debug!("[MCP/{}] Attaching OAuth access token for {}", tag, name);
+    headers.retain(|(k, _)| !k.eq_ignore_ascii_case("authorization"));
+    headers.push(("Authorization".to_string(), format!("Bearer {}", token)));
+  }
+  if headers.is_empty() {
+    return Ok(plain);
+  }
+  remote_client(config, base, tag, headers)
+}
+
+/// 服务器返回 401 时提示用户先完成授权
+fn with_auth_hint(error: String) -> String {
+  if error.contains("401") || error.to_ascii_lowercase().contains("unauthorized") {
+    format!(
+      "{}. The server requires authorization; authorize it and reconnect",
+      error
+    )
+  } else {
+    error
+  }
+}
+
+/// 判断 host 是否为本地或私有网段（用于自动绕过代理）
+pub(crate) fn is_local_or_private(host: &str) -> bool {
+  let lower = host.to_ascii_lowercase();
+  if lower == "localhost" {
+    return true;
+  }
+  if let Ok(ip) = host.parse::<IpAddr>() {
+    match ip {
+      IpAddr::V4(v4) => {
+        if v4.is_loopback() || v4.is_private() {
+          return true;
+        }
+        // 额外常见内网广播/链路本地
+        if v4.octets()[0] == 169 && v4.octets()[1] == 254 {
+          return true;
+        }
+        false
+      }
+      IpAddr::V6(v6) => {
+        // 回环或链路本地
+        v6.is_loopback() || v6.is_unicast_link_local()
+      }
+    }
+  } else {
+    // 域名无法判定，视为非本地
+    false
+  }
+}
@@ -0,0 +1,530 @@
+use crate::env_setup::EnvironmentSetup;
+use crate::mcp::connect::{self, connect_session, is_local_or_private};
+use crate::mcp::expand;
+use crate::mcp::policy;
+use crate::mcp::sandbox;
+use crate::mcp::state::McpState;
+use crate::mcp::types::McpServerConfig;
+use reqwest::Url;
+use rmcp::model::{InitializeResult, ProtocolVersion};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use tokio::time::{timeout, Duration, Instant};
+
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// 与 stdio 首次连接的超时保持一致（npx 首次运行需要下载）
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpDiagnosticStatus {
+  Ok,
+  Warn,
+  Fail,
+  /// 前置步骤失败，未执行
+  Skipped,
+}
+
+/// 诊断报告中的一步
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpDiagnosticStep {
+  /// 步骤标识：config / policy / executable / cwd / pathArgs / sandbox /
+  /// url / proxy / dns / tcp / http / initialize / protocol / capabilities
+  pub id: &'static str,
+  pub status: McpDiagnosticStatus,
+  pub detail: String,
+  pub duration_ms: u64,
+}
+
+/// `mcp_diagnose` 的结果：按执行顺序列出各检查步骤
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpDiagnosticReport {
+  pub server_name: String,
+  /// 没有任何步骤失败
+  pub ok: bool,
+  /// 握手成功时实际使用的传输方式
+  pub transport: Option<String>,
+  /// 握手耗时（毫秒）
+  pub initialize_ms: Option<u64>,
+  /// 服务器在 initialize 中返回的协议版本、能力与实现信息
+  pub server: Option<InitializeResult>,
+  pub steps: Vec<McpDiagnosticStep>,
+}
+
+struct Steps {
+  steps: Vec<McpDiagnosticStep>,
+}
+
+impl Steps {
+  fn push(&mut self, id: &'static str, started: Instant, result: Result<String, String>) {
+    let (status, detail) = match result {
+      Ok(detail) => (McpDiagnosticStatus::Ok, detail),
+      Err(detail) => (McpDiagnosticStatus::Fail, detail),
+    };
+    self.add(id, status, detail, started);
+  }
+
+  fn add(
+    &mut self,
+    id: &'static str,
+    status: McpDiagnosticStatus,
+    detail: String,
+    started: Instant,
+  ) {
+    self.steps.push(McpDiagnosticStep {
+      id,
+      status,
+      detail,
+      duration_ms: started.elapsed().as_millis() as u64,
+    });
+  }
+
+  fn skip(&mut self, ids: &[&'static str]) {
+    for id in ids {
+      self.add(
+        id,
+        McpDiagnosticStatus::Skipped,
+        "skipped because an earlier step failed".into(),
+        Instant::now(),
+      );
+    }
+  }
+
+  fn failed(&self) -> bool {
+    self
+      .steps
+      .iter()
+      .any(|s| s.status == McpDiagnosticStatus::Fail)
+  }
+}
+
+/// 依次检查配置、本地环境或网络可达性，最后进行一次真实的 initialize 握手（完成后立即断开）。
+/// 不影响同名服务器已有的连接。策略要求确认的 stdio 命令在 `confirmed` 为 false 时不会被启动。
+pub(crate) async fn run(
+  app: &AppHandle,
+  server_name: &str,
+  config: &McpServerConfig,
+  confirmed: bool,
+) -> McpDiagnosticReport {
+  let mut steps = Steps { steps: Vec::new() };
+  let mut report = McpDiagnosticReport {
+    server_name: server_name.to_string(),
+    ok: false,
+    transport: None,
+    initialize_ms: None,
+    server: None,
+    steps: Vec::new(),
+  };
+
+  let started = Instant::now();
+  let resolved = expand::resolve_config(app, config);
+  let resolved = match resolved {
+    Ok(resolved) => {
+      steps.push(
+        "config",
+        started,
+        Ok(format!("transport type: {}", resolved.r#type)),
+      );
+      resolved
+    }
+    Err(e) => {
+      steps.push("config", started, Err(e));
+      steps.skip(&["initialize"]);
+      report.steps = steps.steps;
+      return report;
+    }
+  };
+
+  if resolved.r#type == "stdio" {
+    check_stdio(app, server_name, config, &resolved, confirmed, &mut steps).await;
+  } else {
+    check_remote(&resolved, &mut steps).await;
+  }
+
+  if steps.failed() {
+    steps.skip(&["initialize"]);
+  } else {
+    initialize(app, server_name, config, confirmed, &mut steps, &mut report).await;
+  }
+
+  report.ok = !steps.failed();
+  report.steps = steps.steps;
+  report
+}
+
+async fn check_stdio(
+  app: &AppHandle,
+  server_name: &str,
+  raw: &McpServerConfig,
+  config: &McpServerConfig,
+  confirmed: bool,
+  steps: &mut Steps,
+) {
+  let started = Instant::now();
+  let decision = policy::check(app, config, &raw.args);
+  if !decision.allowed {
+    steps.push("policy", started, Err(policy::denial_message(&decision)));
+  } else if decision.requires_confirmation && !confirmed {
+    // 与 mcp_connect 一致：未确认时不启动命令，前端确认后带 confirmed 重新诊断
+    steps.push(
+      "policy",
+      started,
+      Err(format!(
+        "{}: {}",
+        policy::CONFIRMATION_REQUIRED,
+        decision.reasons.join("; ")
+      )),
+    );
+  } else if decision.requires_confirmation {
+    steps.add(
+      "policy",
+      McpDiagnosticStatus::Warn,
+      format!("confirmed by user: {}", decision.reasons.join("; ")),
+      started,
+    );
+  } else {
+    steps.push("policy", started, Ok("allowed".into()));
+  }
+
+  let started = Instant::now();
+  let command = config.command.clone().unwrap_or_default();
+  if command.is_empty() {
+    steps.push(
+      "executable",
+      started,
+      Err("command required for stdio".into()),
+    );
+  } else if command.contains('/') || command.contains('\\') {
+    let result = if Path::new(&command).is_file() {
+      Ok(command.clone())
+    } else {
+      Err(format!("{} does not exist", command))
+    };
+    steps.push("executable", started, result);
+  } else {
+    let found = EnvironmentSetup::new().check_tool_availability_detailed(&command);
+    if let Some(path) = found.path {
+      steps.push("executable", started, Ok(path));
+    } else {
+      // 与连接时一致：找不到时尝试补全 PATH（如 nvm、Homebrew 目录）后再查一次
+      let mut env = EnvironmentSetup::new();
+      let refreshed = env
+        .setup()
+        .ok()
+        .map(|_| env.check_tool_availability_detailed(&command))
+        .and_then(|t| t.path);
+      match refreshed {
+        Some(path) => steps.add(
+          "executable",
+          McpDiagnosticStatus::Warn,
+          format!("{} (found only after extending PATH)", path),
+          started,
+        ),
+        None => steps.push(
+          "executable",
+          started,
+          Err(
+            found
+              .error_message
+              .unwrap_or_else(|| format!("{} not found in PATH", command)),
+          ),
+        ),
+      }
+    }
+  }
+
+  if let Some(cwd) = &config.cwd {
+    let started = Instant::now();
+    // 展示原始写法，展开后的值可能含有环境变量或密钥
+    let shown = raw.cwd.as_deref().unwrap_or(cwd);
+    let result = if Path::new(cwd).is_dir() {
+      Ok(shown.to_string())
+    } else {
+      Err(format!("working directory {} does not exist", shown))
+    };
+    steps.push("cwd", started, result);
+  }
+
+  let started = Instant::now();
+  let args = config.args.clone().unwrap_or_default();
+  // 与连接时一致：检查展开后的参数，但报告原始写法，避免把密钥写进诊断结果
+  let raw_args = raw.args.as_deref().unwrap_or(&args);
+  let missing = connect::missing_path_args(&command, &args, raw_args, config.cwd.as_deref()).await;
+  if missing.is_empty() {
+    steps.push("pathArgs", started, Ok("all path arguments exist".into()));
+  } else {
+    steps.push(
+      "pathArgs",
+      started,
+      Err(connect::missing_paths_message(&missing)),
+    );
+  }
+
+  if config.sandbox.as_ref().is_some_and(|s| s.enabled) {
+    let started = Instant::now();
+    let result = sandbox::plan(app, server_name, config).map(|_| "available".to_string());
+    steps.push("sandbox", started, result);
+  }
+}
+
+async fn check_remote(config: &McpServerConfig, steps: &mut Steps) {
+  let started = Instant::now();
+  let base = config.base_url.clone().unwrap_or_default();
+  let url = match Url::parse(&base) {
+    Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => url,
+    Ok(url) => {
+      steps.push("url", started, Err(format!("unsupported URL: {}", url)));
+      return;
+    }
+    Err(e) => {
+      steps.push(
+        "url",
+        started,
+        Err(format!("invalid baseUrl {:?}: {}", base, e)),
+      );
+      return;
+    }
+  };
+  steps.push("url", started, Ok(url.to_string()));
+
+  // 与 remote_client 的判断一致：本地/私网地址不走代理
+  let started = Instant::now();
+  let host = url.host_str().unwrap_or_default().to_string();
+  let proxy = config
+    .proxy_url
+    .as_deref()
+    .filter(|_| config.use_proxy.unwrap_or(false))
+    .filter(|_| !is_local_or_private(&host));
+  let proxy_url = match proxy.map(Url::parse) {
+    Some(Ok(p)) => {
+      steps.push("proxy", started, Ok(format!("via proxy {}", p)));
+      Some(p)
+    }
+    Some(Err(e)) => {
+      steps.push("proxy", started, Err(format!("invalid proxyUrl: {}", e)));
+      return;
+    }
+    None if config.use_proxy.unwrap_or(false) && is_local_or_private(&host) => {
+      steps.push(
+        "proxy",
+        started,
+        Ok("direct (local or private address bypasses the proxy)".into()),
+      );
+      None
+    }
+    None => {
+      steps.push("proxy", started, Ok("direct".into()));
+      None
+    }
+  };
+
+  // 走代理时检查到代理的连通性，否则检查到目标服务器
+  let target = proxy_url.as_ref().unwrap_or(&url);
+  let target_host = target.host_str().unwrap_or_default().to_string();
+  let Some(port) = target.port_or_known_default() else {
+    steps.push(
+      "dns",
+      Instant::now(),
+      Err(format!("no port for {}", target)),
+    );
+    return;
+  };
+
+  let started = Instant::now();
+  let addrs: Vec<SocketAddr> = match timeout(
+    NETWORK_TIMEOUT,
+    tokio::net::lookup_host((target_host.as_str(), port)),
+  )
+  .await
+  {
+    Ok(Ok(addrs)) => addrs.collect(),
+    Ok(Err(e)) => {
+      steps.push("dns", started, Err(format!("{}: {}", target_host, e)));
+      steps.skip(&["tcp", "http"]);
+      return;
+    }
+    Err(_) => {
+      steps.push(
+        "dns",
+        started,
+        Err(format!("{}: lookup timed out", target_host)),
+      );
+      steps.skip(&["tcp", "http"]);
+      return;
+    }
+  };
+  let listed: Vec<String> = addrs.iter().map(|a| a.ip().to_string()).collect();
+  steps.push(
+    "dns",
+    started,
+    Ok(format!("{} -> {}", target_host, listed.join(", "))),
+  );
+
+  let started = Instant::now();
+  let mut last_error = format!("{} has no addresses", target_host);
+  let mut connected = None;
+  for addr in &addrs {
+    match timeout(NETWORK_TIMEOUT, tokio::net::TcpStream::connect(addr)).await {
+      Ok(Ok(_)) => {
+        connected = Some(*addr);
+        break;
+      }
+      Ok(Err(e)) => last_error = format!("{}: {}", addr, e),
+      Err(_) => last_error = format!("{}: connect timed out", addr),
+    }
+  }
+  match connected {
+    Some(addr) => steps.push("tcp", started, Ok(format!("connected to {}", addr))),
+    None => {
+      steps.push("tcp", started, Err(last_error));
+      steps.skip(&["http"]);
+      return;
+    }
+  };
+
+  // 任意 HTTP 状态码都说明 TLS 握手与 HTTP 层正常（MCP 端点对 GET 可能返回 4xx）
+  let started = Instant::now();
+  let client = match connect::remote_client(config, url.as_str(), "diagnose", Vec::new()) {
+    Ok(c) => c,
+    Err(e) => {
+      steps.push("http", started, Err(e));
+      return;
+    }
+  };
+  let tls = if url.scheme() == "https" {
+    "TLS ok, "
+  } else {
+    ""
+  };
+  let result = client
+    .get(url.as_str())
+    .header(reqwest::header::ACCEPT, "text/event-stream")
+    .timeout(HTTP_TIMEOUT)
+    .send()
+    .await
+    .map(|resp| format!("{}HTTP {}", tls, resp.status()))
+    .map_err(|e| {
+      // 展开错误链，TLS 证书问题通常在 source 中
+      let mut msg = e.to_string();
+      let mut source = std::error::Error::source(&e);
+      while let Some(s) = source {
+        msg.push_str(&format!(": {}", s));
+        source = s.source();
+      }
+      msg
+    });
+  steps.push("http", started, result);
+}
+
+async fn initialize(
+  app: &AppHandle,
+  server_name: &str,
+  config: &McpServerConfig,
+  confirmed: bool,
+  steps: &mut Steps,
+  report: &mut McpDiagnosticReport,
+) {
+  // 临时连接使用独立的会话名，子进程、stderr 与客户端事件不会记到正在运行的同名服务器上
+  let session = format!("{}#diagnose", server_name);
+
+  let started = Instant::now();
+  let connected = match timeout(
+    INITIALIZE_TIMEOUT,
+    connect_session(app, server_name, &session, config, confirmed),
+  )
+  .await
+  {
+    Ok(r) => r,
+    Err(_) => Err(format!(
+      "initialize timed out after {}s",
+      INITIALIZE_TIMEOUT.as_secs()
+    )),
+  };
+  let (service, transport) = match connected {
+    Ok(c) => c,
+    Err(e) => {
+      steps.push("initialize", started, Err(e));
+      forget_session(app, &session);
+      return;
+    }
+  };
+  let initialize_ms = started.elapsed().as_millis() as u64;
+  steps.push(
+    "initialize",
+    started,
+    Ok(format!(
+      "handshake over {} in {}ms",
+      transport, initialize_ms
+    )),
+  );
+  report.transport = Some(transport.to_string());
+  report.initialize_ms = Some(initialize_ms);
+
+  if let Some(info) = service.peer().peer_info().cloned() {
+    let started = Instant::now();
+    let version = info.protocol_version.to_string();
+    if info.protocol_version == ProtocolVersion::LATEST {
+      steps.push("protocol", started, Ok(version));
+    } else {
+      steps.add(
+        "protocol",
+        McpDiagnosticStatus::Warn,
+        format!(
+          "server negotiated {} (client prefers {})",
+          version,
+          ProtocolVersion::LATEST
+        ),
+        started,
+      );
+    }
+
+    let caps = &info.capabilities;
+    let advertised: Vec<&str> = [
+      ("tools", caps.tools.is_some()),
+      ("resources", caps.resources.is_some()),
+      ("prompts", caps.prompts.is_some()),
+      ("logging", caps.logging.is_some()),
+      ("completions", caps.completions.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(name, present)| present.then_some(name))
+    .collect();
+    let detail = format!(
+      "{} {}: {}",
+      info.server_info.name,
+      info.server_info.version,
+      if advertised.is_empty() {
+        "no capabilities".to_string()
+      } else {
+        advertised.join(", ")
+      }
+    );
+    let status = if advertised.is_empty() {
+      McpDiagnosticStatus::Warn
+    } else {
+      McpDiagnosticStatus::Ok
+    };
+    steps.add("capabilities", status, detail, Instant::now());
+    report.server = Some(info);
+  }
+
+  if let Err(e) = service.cancel().await {
+    log::warn!(
+      "[MCP/diagnose] Failed to close diagnostic connection: {}",
+      e
+    );
+  }
+  forget_session(app, &session);
+}
+
+/// 清理临时连接留下的子进程记录与列表缓存；stderr 缓冲保留，便于查看诊断时的输出
+fn forget_session(app: &AppHandle, session: &str) {
+  let state = app.state::<McpState>();
+  state.child_pids.remove(session);
+  state.listings.remove(session);
+}
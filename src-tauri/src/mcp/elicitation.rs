@@ -0,0 +1,151 @@
+use crate::mcp::schema::{self, SchemaError};
+use crate::mcp::state::McpState;
+use rmcp::model::{
+  CreateElicitationRequestParam, CreateElicitationResult, ElicitationAction, ErrorData as McpError,
+};
+use rmcp::service::{RequestContext, RoleClient};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::time::{timeout, Duration};
+
+/// 服务器请求用户输入时推送给前端的事件名
+pub const ELICITATION_REQUEST_EVENT: &str = "mcp-elicitation-request";
+
+/// 等待用户填写的最长时间，超时按 cancel 回复服务器
+const ELICITATION_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ElicitationRequestEvent {
+  request_id: String,
+  server_name: String,
+  message: String,
+  requested_schema: Value,
+}
+
+/// 前端对 elicitation 请求的答复（经 `mcp_respond_elicitation` 回填）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpElicitationResponse {
+  /// accept | decline | cancel
+  pub action: ElicitationAction,
+  /// 仅 accept 时有效，需满足请求中的 requestedSchema
+  pub content: Option<Value>,
+}
+
+/// 校验用户的回复：accept 时内容必须是满足 requestedSchema 的对象
+pub(crate) fn check_reply(
+  requested_schema: &Value,
+  response: &McpElicitationResponse,
+) -> Result<(), Vec<SchemaError>> {
+  if response.action != ElicitationAction::Accept {
+    return Ok(());
+  }
+  let content = response.content.clone().unwrap_or(Value::Null);
+  let errors = schema::validate(requested_schema, &content);
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(errors)
+  }
+}
+
+/// 将 elicitation 请求转交前端，等待用户 accept / decline / cancel
+pub(crate) async fn request_input(
+  app: &AppHandle,
+  server_name: &str,
+  request: CreateElicitationRequestParam,
+  context: RequestContext<RoleClient>,
+) -> Result<CreateElicitationResult, McpError> {
+  let state = app.state::<McpState>();
+  let requested_schema = serde_json::to_value(&request.requested_schema).unwrap_or(Value::Null);
+  let (request_id, rx) = state.elicitations.register(requested_schema.clone());
+  log::info!(
+    "[MCP/elicitation] Server {} requested user input, requestId={}",
+    server_name,
+    request_id
+  );
+
+  let event = ElicitationRequestEvent {
+    request_id: request_id.clone(),
+    server_name: server_name.to_string(),
+    message: request.message,
+    requested_schema,
+  };
+  if let Err(e) = app.emit(ELICITATION_REQUEST_EVENT, event) {
+    state.elicitations.discard(&request_id);
+    return Err(McpError::internal_error(
+      format!("Failed to forward elicitation request: {}", e),
+      None,
+    ));
+  }
+
+  let cancelled = CreateElicitationResult {
+    action: ElicitationAction::Cancel,
+    content: None,
+  };
+  let reply = tokio::select! {
+    r = timeout(ELICITATION_TIMEOUT, rx) => r,
+    _ = context.ct.cancelled() => {
+      log::info!("[MCP/elicitation] Request {} cancelled by server", request_id);
+      state.elicitations.discard(&request_id);
+      return Ok(cancelled);
+    }
+  };
+  let reply = match reply {
+    Ok(Ok(reply)) => reply,
+    Ok(Err(_)) => return Ok(cancelled),
+    Err(_) => {
+      log::warn!("[MCP/elicitation] Request {} timed out", request_id);
+      state.elicitations.discard(&request_id);
+      return Ok(cancelled);
+    }
+  };
+
+  log::info!(
+    "[MCP/elicitation] Request {} answered with {:?}",
+    request_id,
+    reply.action
+  );
+  let content = match reply.action {
+    ElicitationAction::Accept => reply.content,
+    _ => None,
+  };
+  Ok(CreateElicitationResult {
+    action: reply.action,
+    content,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_check_reply_only_validates_accept() {
+    let schema = json!({
+      "type": "object",
+      "properties": { "email": { "type": "string" } },
+      "required": ["email"]
+    });
+    let decline = McpElicitationResponse {
+      action: ElicitationAction::Decline,
+      content: None,
+    };
+    assert!(check_reply(&schema, &decline).is_ok());
+
+    let bad = McpElicitationResponse {
+      action: ElicitationAction::Accept,
+      content: Some(json!({})),
+    };
+    assert!(check_reply(&schema, &bad).is_err());
+
+    let good = McpElicitationResponse {
+      action: ElicitationAction::Accept,
+      content: Some(json!({ "email": "a@b.c" })),
+    };
+    assert!(check_reply(&schema, &good).is_ok());
+  }
+}
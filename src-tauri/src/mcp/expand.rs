@@ -0,0 +1,183 @@
+use crate::mcp::types::McpServerConfig;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// 密钥的持久化文件（key 为密钥名）；服务器配置中以 `${secret:NAME}` 引用
+const SECRETS_STORE: &str = "mcp-secrets.json";
+
+/// inheritEnv=false 时仍保留的基础变量，缺少它们大多数运行时无法启动
+pub(crate) const BASELINE_ENV: [&str; 14] = [
+  "PATH",
+  "HOME",
+  "USER",
+  "LANG",
+  "TMPDIR",
+  "TEMP",
+  "TMP",
+  "SYSTEMROOT",
+  "SYSTEMDRIVE",
+  "COMSPEC",
+  "PATHEXT",
+  "USERPROFILE",
+  "APPDATA",
+  "LOCALAPPDATA",
+];
+
+fn home_dir() -> Option<String> {
+  std::env::var("HOME")
+    .or_else(|_| std::env::var("USERPROFILE"))
+    .ok()
+}
+
+/// 展开单个字符串：
+/// - 开头的 `~`（后接路径分隔符或结尾）→ 用户主目录
+/// - `${VAR}` / `${VAR:-默认值}` → 环境变量
+/// - `${secret:NAME}` → 密钥存储中的值
+/// - `$$` → 字面量 `$`
+///
+/// 引用的变量或密钥不存在时返回错误，而不是静默替换为空串。
+pub(crate) fn expand_str(
+  input: &str,
+  env: &dyn Fn(&str) -> Option<String>,
+  secret: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+  let mut rest = input;
+  let mut out = String::with_capacity(input.len());
+
+  if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+    out.push_str(
+      &home_dir().ok_or_else(|| "Cannot expand '~': home directory unknown".to_string())?,
+    );
+    rest = &rest[1..];
+  }
+
+  while let Some(pos) = rest.find('$') {
+    out.push_str(&rest[..pos]);
+    let after = &rest[pos + 1..];
+    if let Some(stripped) = after.strip_prefix('$') {
+      out.push('$');
+      rest = stripped;
+    } else if let Some(body) = after.strip_prefix('{') {
+      let end = body
+        .find('}')
+        .ok_or_else(|| format!("Unclosed '${{' in '{}'", input))?;
+      let expr = &body[..end];
+      let value = if let Some(name) = expr.strip_prefix("secret:") {
+        secret(name).ok_or_else(|| format!("Secret '{}' not found", name))?
+      } else {
+        let (name, default) = match expr.split_once(":-") {
+          Some((n, d)) => (n, Some(d)),
+          None => (expr, None),
+        };
+        match (env(name), default) {
+          (Some(v), _) => v,
+          (None, Some(d)) => d.to_string(),
+          (None, None) => return Err(format!("Environment variable '{}' is not set", name)),
+        }
+      };
+      out.push_str(&value);
+      rest = &body[end + 1..];
+    } else {
+      // 不是变量引用，按字面量保留
+      out.push('$');
+      rest = after;
+    }
+  }
+  out.push_str(rest);
+  Ok(out)
+}
+
+fn load_secret(app: &AppHandle, name: &str) -> Option<String> {
+  let store = app.store(SECRETS_STORE).ok()?;
+  store
+    .get(name)
+    .and_then(|v| v.as_str().map(|s| s.to_string()))
+}
+
+/// 返回展开后的配置副本：args、env 值、cwd、baseUrl 与 headers 值均参与展开。
+/// command 不展开：策略规则与启动确认针对的是用户看到的字面命令，不允许由变量决定启动哪个程序
+pub(crate) fn resolve_config(
+  app: &AppHandle,
+  config: &McpServerConfig,
+) -> Result<McpServerConfig, String> {
+  let env = |name: &str| std::env::var(name).ok();
+  let secret = |name: &str| load_secret(app, name);
+  let expand = |s: &String| expand_str(s, &env, &secret);
+  let expand_pairs = |pairs: &Vec<(String, String)>| {
+    pairs
+      .iter()
+      .map(|(k, v)| Ok((k.clone(), expand(v)?)))
+      .collect::<Result<Vec<_>, String>>()
+  };
+
+  let mut resolved = config.clone();
+  resolved.args = config
+    .args
+    .as_ref()
+    .map(|a| a.iter().map(expand).collect::<Result<Vec<_>, _>>())
+    .transpose()?;
+  resolved.env = config.env.as_ref().map(expand_pairs).transpose()?;
+  resolved.headers = config.headers.as_ref().map(expand_pairs).transpose()?;
+  resolved.base_url = config.base_url.as_ref().map(expand).transpose()?;
+  resolved.cwd = config.cwd.as_ref().map(expand).transpose()?;
+
+  if let Some(cwd) = &resolved.cwd {
+    if !PathBuf::from(cwd).is_dir() {
+      return Err(format!("Working directory does not exist: {}", cwd));
+    }
+  }
+  Ok(resolved)
+}
+
+pub(crate) fn set_secret(app: &AppHandle, name: &str, value: &str) -> Result<(), String> {
+  let store = app
+    .store(SECRETS_STORE)
+    .map_err(|e| format!("Failed to open secrets store: {}", e))?;
+  store.set(name, serde_json::Value::String(value.to_string()));
+  store
+    .save()
+    .map_err(|e| format!("Failed to save secrets store: {}", e))
+}
+
+pub(crate) fn delete_secret(app: &AppHandle, name: &str) -> Result<(), String> {
+  let store = app
+    .store(SECRETS_STORE)
+    .map_err(|e| format!("Failed to open secrets store: {}", e))?;
+  store.delete(name);
+  store
+    .save()
+    .map_err(|e| format!("Failed to save secrets store: {}", e))
+}
+
+/// 仅返回密钥名，值不会回传前端
+pub(crate) fn list_secrets(app: &AppHandle) -> Result<Vec<String>, String> {
+  let store = app
+    .store(SECRETS_STORE)
+    .map_err(|e| format!("Failed to open secrets store: {}", e))?;
+  let mut keys = store.keys();
+  keys.sort();
+  Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn expand(input: &str) -> Result<String, String> {
+    let env = |name: &str| (name == "TOKEN_DIR").then(|| "/data".to_string());
+    let secret = |name: &str| (name == "gh").then(|| "s3cr3t".to_string());
+    expand_str(input, &env, &secret)
+  }
+
+  #[test]
+  fn test_expand_variables_and_secrets() {
+    assert_eq!(expand("${TOKEN_DIR}/x").unwrap(), "/data/x");
+    assert_eq!(expand("--token=${secret:gh}").unwrap(), "--token=s3cr3t");
+    assert_eq!(expand("${MISSING:-fallback}").unwrap(), "fallback");
+    assert_eq!(expand("cost $5 and $$HOME").unwrap(), "cost $5 and $HOME");
+    assert!(expand("${MISSING}").is_err());
+    assert!(expand("${secret:other}").is_err());
+    assert!(expand("${TOKEN_DIR").is_err());
+  }
+}
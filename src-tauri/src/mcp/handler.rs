@@ -0,0 +1,198 @@
+use crate::mcp::cache::{self, ListingKind};
+use crate::mcp::state::McpState;
+use crate::mcp::{elicitation, roots, sampling};
+use rmcp::{
+  model::{
+    ClientCapabilities, ClientInfo, CreateElicitationRequestParam, CreateElicitationResult,
+    CreateMessageRequestParam, CreateMessageResult, ErrorData as McpError, Implementation,
+    ListRootsResult, LoggingLevel, LoggingMessageNotificationParam, ProgressNotificationParam,
+    ResourceUpdatedNotificationParam, RootsCapabilities,
+  },
+  service::{NotificationContext, RequestContext, RoleClient},
+  ClientHandler,
+};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+// —— 转发给前端的事件名（payload 均带 serverName） ——
+pub const PROGRESS_EVENT: &str = "mcp-progress";
+pub const LOG_EVENT: &str = "mcp-log";
+pub const LIST_CHANGED_EVENT: &str = "mcp-list-changed";
+pub const RESOURCE_UPDATED_EVENT: &str = "mcp-resource-updated";
+
+/// 事件负载：在原始通知参数外附加服务器名
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ServerEvent<T> {
+  server_name: String,
+  #[serde(flatten)]
+  payload: T,
+}
+
+/// 进度事件负载：附上对应工具调用的 callId（由本应用发起的调用才有）
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  call_id: Option<String>,
+  #[serde(flatten)]
+  params: ProgressNotificationParam,
+}
+
+#[derive(Serialize, Clone)]
+struct ListChanged {
+  kind: &'static str,
+}
+
+/// 每个 MCP 连接各持有一个 handler，将服务器推送的通知以 Tauri 事件形式转发给前端
+#[derive(Clone)]
+pub struct McpClientHandler {
+  app: AppHandle,
+  server_name: String,
+}
+
+impl McpClientHandler {
+  pub fn new(app: AppHandle, server_name: &str) -> Self {
+    Self {
+      app,
+      server_name: server_name.to_string(),
+    }
+  }
+
+  fn emit<T: Serialize + Clone>(&self, event: &str, payload: T) {
+    let evt = ServerEvent {
+      server_name: self.server_name.clone(),
+      payload,
+    };
+    if let Err(e) = self.app.emit(event, evt) {
+      log::warn!(
+        "[MCP] Failed to emit {} for server {}: {}",
+        event,
+        self.server_name,
+        e
+      );
+    }
+  }
+
+  /// 失效对应的列表缓存后再通知前端，保证前端随后拉取到的是新列表
+  fn on_list_changed(&self, kind: ListingKind) {
+    log::debug!(
+      "[MCP] {:?} list changed on server {}",
+      kind,
+      self.server_name
+    );
+    cache::invalidate(&self.app.state::<McpState>(), &self.server_name, kind);
+    let kind = match kind {
+      ListingKind::Tools => "tools",
+      ListingKind::Resources => "resources",
+      ListingKind::Prompts => "prompts",
+    };
+    self.emit(LIST_CHANGED_EVENT, ListChanged { kind });
+  }
+}
+
+impl ClientHandler for McpClientHandler {
+  async fn create_message(
+    &self,
+    params: CreateMessageRequestParam,
+    context: RequestContext<RoleClient>,
+  ) -> Result<CreateMessageResult, McpError> {
+    sampling::request_completion(&self.app, &self.server_name, params, context).await
+  }
+
+  async fn list_roots(
+    &self,
+    _context: RequestContext<RoleClient>,
+  ) -> Result<ListRootsResult, McpError> {
+    let roots = roots::load_roots(&self.app, &self.server_name);
+    log::debug!(
+      "[MCP/roots] Server {} listed {} roots",
+      self.server_name,
+      roots.len()
+    );
+    Ok(ListRootsResult { roots })
+  }
+
+  async fn create_elicitation(
+    &self,
+    request: CreateElicitationRequestParam,
+    context: RequestContext<RoleClient>,
+  ) -> Result<CreateElicitationResult, McpError> {
+    elicitation::request_input(&self.app, &self.server_name, request, context).await
+  }
+
+  async fn on_progress(
+    &self,
+    params: ProgressNotificationParam,
+    _context: NotificationContext<RoleClient>,
+  ) {
+    let call_id = self
+      .app
+      .state::<McpState>()
+      .progress_calls
+      .get(&(self.server_name.clone(), params.progress_token.clone()))
+      .map(|c| c.clone());
+    self.emit(PROGRESS_EVENT, Progress { call_id, params });
+  }
+
+  async fn on_logging_message(
+    &self,
+    params: LoggingMessageNotificationParam,
+    _context: NotificationContext<RoleClient>,
+  ) {
+    // 同步写入应用日志，便于事后排查
+    match params.level {
+      LoggingLevel::Debug => log::debug!("[MCP/{}] {}", self.server_name, params.data),
+      LoggingLevel::Info | LoggingLevel::Notice => {
+        log::info!("[MCP/{}] {}", self.server_name, params.data)
+      }
+      LoggingLevel::Warning => log::warn!("[MCP/{}] {}", self.server_name, params.data),
+      _ => log::error!("[MCP/{}] {}", self.server_name, params.data),
+    }
+    self.emit(LOG_EVENT, params);
+  }
+
+  async fn on_resource_updated(
+    &self,
+    params: ResourceUpdatedNotificationParam,
+    _context: NotificationContext<RoleClient>,
+  ) {
+    log::debug!(
+      "[MCP] Resource {} updated on server {}",
+      params.uri,
+      self.server_name
+    );
+    self.emit(RESOURCE_UPDATED_EVENT, params);
+  }
+
+  async fn on_tool_list_changed(&self, _context: NotificationContext<RoleClient>) {
+    self.on_list_changed(ListingKind::Tools);
+  }
+
+  async fn on_resource_list_changed(&self, _context: NotificationContext<RoleClient>) {
+    self.on_list_changed(ListingKind::Resources);
+  }
+
+  async fn on_prompt_list_changed(&self, _context: NotificationContext<RoleClient>) {
+    self.on_list_changed(ListingKind::Prompts);
+  }
+
+  fn get_info(&self) -> ClientInfo {
+    ClientInfo {
+      capabilities: ClientCapabilities {
+        roots: Some(RootsCapabilities {
+          list_changed: Some(true),
+        }),
+        sampling: Some(Default::default()),
+        elicitation: Some(Default::default()),
+        ..Default::default()
+      },
+      client_info: Implementation {
+        name: "chatless".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        ..Default::default()
+      },
+      ..Default::default()
+    }
+  }
+}
@@ -0,0 +1,379 @@
+use crate::mcp::policy::{self, StdioPolicy};
+use crate::mcp::types::McpServerConfig;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+/// 一个候选配置来源（已知客户端的配置文件或用户指定的文件）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpImportSource {
+  pub client: String,
+  pub path: String,
+  pub found: bool,
+  pub error: Option<String>,
+  pub count: usize,
+}
+
+/// 预览中的单个服务器；config 为空表示无法转换
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpImportCandidate {
+  pub name: String,
+  pub client: String,
+  pub source_path: String,
+  pub config: Option<McpServerConfig>,
+  /// 原配置中被标记为禁用
+  pub disabled: bool,
+  /// 是否能通过当前 stdio 策略（远程服务器恒为 true）
+  pub allowed: bool,
+  pub requires_confirmation: bool,
+  /// 策略拒绝原因与转换警告
+  pub issues: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpImportPreview {
+  pub sources: Vec<McpImportSource>,
+  pub servers: Vec<McpImportCandidate>,
+}
+
+fn home() -> Option<PathBuf> {
+  std::env::var_os("HOME")
+    .or_else(|| std::env::var_os("USERPROFILE"))
+    .map(PathBuf::from)
+}
+
+/// 各平台“应用配置目录”：macOS 为 ~/Library/Application Support，Windows 为 %APPDATA%，其它为 ~/.config
+fn config_root() -> Option<PathBuf> {
+  if cfg!(target_os = "macos") {
+    home().map(|h| h.join("Library").join("Application Support"))
+  } else if cfg!(target_os = "windows") {
+    std::env::var_os("APPDATA").map(PathBuf::from)
+  } else {
+    std::env::var_os("XDG_CONFIG_HOME")
+      .map(PathBuf::from)
+      .or_else(|| home().map(|h| h.join(".config")))
+  }
+}
+
+/// 已知客户端的全局配置文件位置
+pub(crate) fn known_locations() -> Vec<(&'static str, PathBuf)> {
+  let mut out = Vec::new();
+  if let Some(root) = config_root() {
+    out.push((
+      "Claude Desktop",
+      root.join("Claude").join("claude_desktop_config.json"),
+    ));
+    out.push(("VS Code", root.join("Code").join("User").join("mcp.json")));
+    out.push((
+      "VS Code",
+      root.join("Code").join("User").join("settings.json"),
+    ));
+  }
+  if let Some(h) = home() {
+    out.push(("Cursor", h.join(".cursor").join("mcp.json")));
+    out.push((
+      "Windsurf",
+      h.join(".codeium").join("windsurf").join("mcp_config.json"),
+    ));
+  }
+  out
+}
+
+/// 去除 JSONC 中的注释与尾随逗号（VS Code 的配置文件允许二者）
+pub(crate) fn strip_jsonc(input: &str) -> String {
+  let chars: Vec<char> = input.chars().collect();
+  let mut out = String::with_capacity(input.len());
+  let mut i = 0;
+  let mut in_string = false;
+  while i < chars.len() {
+    let c = chars[i];
+    if in_string {
+      out.push(c);
+      if c == '\\' && i + 1 < chars.len() {
+        out.push(chars[i + 1]);
+        i += 1;
+      } else if c == '"' {
+        in_string = false;
+      }
+    } else if c == '"' {
+      in_string = true;
+      out.push(c);
+    } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+      continue;
+    } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+      i += 2;
+      while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+        i += 1;
+      }
+      i += 2;
+      continue;
+    } else if c == ',' {
+      // 逗号后（跳过空白）紧跟 } 或 ] 即为尾随逗号
+      let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+      if !matches!(next, Some('}') | Some(']')) {
+        out.push(c);
+      }
+    } else {
+      out.push(c);
+    }
+    i += 1;
+  }
+  out
+}
+
+/// 找到服务器表：`mcpServers`（Claude Desktop / Cursor / Windsurf）、
+/// `servers`（VS Code mcp.json）或 `mcp.servers`（VS Code settings.json）
+fn server_table(root: &Value) -> Option<&Map<String, Value>> {
+  root
+    .get("mcpServers")
+    .or_else(|| root.get("servers"))
+    .or_else(|| root.get("mcp").and_then(|m| m.get("servers")))
+    .and_then(Value::as_object)
+}
+
+/// VS Code 的 `${env:VAR}` 转为本应用支持的 `${VAR}`；`${input:..}` 无法转换，记为警告
+fn convert_vars(s: &str, issues: &mut Vec<String>) -> String {
+  if s.contains("${input:") {
+    issues.push(format!(
+      "'{}' uses a VS Code input variable; replace it with a secret reference",
+      s
+    ));
+  }
+  s.replace("${env:", "${")
+}
+
+fn string_pairs(value: Option<&Value>, issues: &mut Vec<String>) -> Option<Vec<(String, String)>> {
+  let obj = value?.as_object()?;
+  let pairs: Vec<(String, String)> = obj
+    .iter()
+    .map(|(k, v)| {
+      let v = match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+      };
+      (k.clone(), convert_vars(&v, issues))
+    })
+    .collect();
+  (!pairs.is_empty()).then_some(pairs)
+}
+
+/// 将单个外部条目转换为 McpServerConfig；返回 (配置, 是否禁用, 警告)
+pub(crate) fn normalize_entry(entry: &Value) -> (Option<McpServerConfig>, bool, Vec<String>) {
+  let mut issues = Vec::new();
+  let disabled = entry
+    .get("disabled")
+    .and_then(Value::as_bool)
+    .unwrap_or(false);
+  let Some(obj) = entry.as_object() else {
+    return (None, disabled, vec!["entry is not an object".to_string()]);
+  };
+  let declared = obj.get("type").and_then(Value::as_str).unwrap_or("");
+
+  if let Some(command) = obj.get("command").and_then(Value::as_str) {
+    let args = obj.get("args").and_then(Value::as_array).map(|a| {
+      a.iter()
+        .map(|v| convert_vars(v.as_str().unwrap_or(&v.to_string()), &mut issues))
+        .collect::<Vec<_>>()
+    });
+    if obj.contains_key("envFile") {
+      issues.push("envFile is not supported; copy the variables into env".to_string());
+    }
+    let config = McpServerConfig {
+      r#type: "stdio".to_string(),
+      command: Some(convert_vars(command, &mut issues)),
+      args,
+      env: string_pairs(obj.get("env"), &mut issues),
+      cwd: obj
+        .get("cwd")
+        .and_then(Value::as_str)
+        .map(|s| convert_vars(s, &mut issues)),
+      ..Default::default()
+    };
+    return (Some(config), disabled, issues);
+  }
+
+  let url = ["url", "serverUrl", "httpUrl"]
+    .iter()
+    .find_map(|k| obj.get(*k).and_then(Value::as_str));
+  let Some(url) = url else {
+    issues.push("entry has neither command nor url".to_string());
+    return (None, disabled, issues);
+  };
+  let transport = match declared.to_ascii_lowercase().as_str() {
+    "sse" => "sse",
+    "http" | "streamable-http" | "streamablehttp" => "http",
+    // 未声明时：httpUrl 为可流式 HTTP，/sse 结尾为旧版 SSE，其余连接时自动探测
+    _ if obj.contains_key("httpUrl") => "http",
+    _ if url.trim_end_matches('/').ends_with("/sse") => "sse",
+    _ => "auto",
+  };
+  let config = McpServerConfig {
+    r#type: transport.to_string(),
+    base_url: Some(convert_vars(url, &mut issues)),
+    headers: string_pairs(obj.get("headers"), &mut issues),
+    ..Default::default()
+  };
+  (Some(config), disabled, issues)
+}
+
+/// 解析一份配置文本，返回其中全部候选服务器
+pub(crate) fn parse_servers(
+  text: &str,
+  client: &str,
+  source_path: &str,
+  policy: &StdioPolicy,
+) -> Result<Vec<McpImportCandidate>, String> {
+  let root: Value =
+    serde_json::from_str(&strip_jsonc(text)).map_err(|e| format!("Invalid JSON: {}", e))?;
+  let table = match server_table(&root) {
+    Some(t) => t,
+    None => return Ok(Vec::new()),
+  };
+
+  Ok(
+    table
+      .iter()
+      .map(|(name, entry)| {
+        let (config, disabled, mut issues) = normalize_entry(entry);
+        let (allowed, requires_confirmation) = match &config {
+          Some(c) if c.r#type == "stdio" => {
+            let decision = policy::evaluate(policy, c);
+            issues.extend(decision.reasons);
+            (decision.allowed, decision.requires_confirmation)
+          }
+          Some(_) => (true, false),
+          None => (false, false),
+        };
+        McpImportCandidate {
+          name: name.clone(),
+          client: client.to_string(),
+          source_path: source_path.to_string(),
+          config,
+          disabled,
+          allowed,
+          requires_confirmation,
+          issues,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// 扫描已知位置与额外路径，并可附带一段用户粘贴的 JSON
+pub(crate) fn preview(
+  extra_paths: &[String],
+  text: Option<&str>,
+  policy: &StdioPolicy,
+) -> McpImportPreview {
+  let mut locations: Vec<(String, PathBuf)> = known_locations()
+    .into_iter()
+    .map(|(c, p)| (c.to_string(), p))
+    .collect();
+  locations.extend(
+    extra_paths
+      .iter()
+      .map(|p| ("File".to_string(), PathBuf::from(p))),
+  );
+
+  let mut sources = Vec::new();
+  let mut servers = Vec::new();
+  for (client, path) in locations {
+    let path_str = path.display().to_string();
+    let mut source = McpImportSource {
+      client: client.clone(),
+      path: path_str.clone(),
+      found: path.is_file(),
+      error: None,
+      count: 0,
+    };
+    if source.found {
+      match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|t| parse_servers(&t, &client, &path_str, policy))
+      {
+        Ok(found) => {
+          source.count = found.len();
+          servers.extend(found);
+        }
+        Err(e) => {
+          log::warn!("[MCP/import] Failed to parse {}: {}", path_str, e);
+          source.error = Some(e);
+        }
+      }
+    }
+    sources.push(source);
+  }
+
+  if let Some(text) = text.filter(|t| !t.trim().is_empty()) {
+    let mut source = McpImportSource {
+      client: "Pasted".to_string(),
+      path: String::new(),
+      found: true,
+      error: None,
+      count: 0,
+    };
+    match parse_servers(text, "Pasted", "", policy) {
+      Ok(found) => {
+        source.count = found.len();
+        servers.extend(found);
+      }
+      Err(e) => source.error = Some(e),
+    }
+    sources.push(source);
+  }
+
+  McpImportPreview { sources, servers }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_claude_desktop_and_vscode_formats() {
+    let policy = StdioPolicy::default();
+    let claude = r#"{ "mcpServers": {
+      "fs": { "command": "npx", "args": ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"], "env": { "A": "1" } },
+      "py": { "command": "python", "args": ["-m", "server"] },
+      "remote": { "url": "https://example.com/sse" }
+    } }"#;
+    let servers = parse_servers(claude, "Claude Desktop", "x", &policy).unwrap();
+    let by_name = |n: &str| servers.iter().find(|s| s.name == n).unwrap();
+    assert!(by_name("fs").allowed);
+    assert_eq!(
+      by_name("fs").config.as_ref().unwrap().env,
+      Some(vec![("A".to_string(), "1".to_string())])
+    );
+    assert!(!by_name("py").allowed);
+    assert!(!by_name("py").issues.is_empty());
+    assert_eq!(by_name("remote").config.as_ref().unwrap().r#type, "sse");
+
+    let vscode = r#"{
+      // comment
+      "servers": {
+        "gh": { "type": "http", "url": "https://api.example.com/mcp", "headers": { "Authorization": "Bearer ${env:GH_TOKEN}" }, },
+      },
+    }"#;
+    let servers = parse_servers(vscode, "VS Code", "y", &policy).unwrap();
+    let cfg = servers[0].config.as_ref().unwrap();
+    assert_eq!(cfg.r#type, "http");
+    assert_eq!(
+      cfg.headers.as_ref().unwrap()[0].1,
+      "Bearer ${GH_TOKEN}".to_string()
+    );
+  }
+
+  #[test]
+  fn test_strip_jsonc_keeps_strings_intact() {
+    let text = r#"{ "a": "http://x//y", /* c */ "b": [1, 2,], }"#;
+    let v: Value = serde_json::from_str(&strip_jsonc(text)).unwrap();
+    assert_eq!(v["a"], "http://x//y");
+    assert_eq!(v["b"].as_array().unwrap().len(), 2);
+  }
+}
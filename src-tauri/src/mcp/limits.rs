@@ -0,0 +1,179 @@
+use crate::mcp::types::McpCallLimits;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{Duration, Instant};
+
+/// 排队超时或超出速率限制时工具调用返回的错误前缀
+pub const RATE_LIMITED: &str = "MCP_RATE_LIMITED";
+/// 未配置 `maxWaitMs` 时排队等待的上限
+const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// 令牌桶：容量为 burst，按 rate（个/秒）匀速补充。
+/// 令牌允许为负，表示已被排队中的调用预订，从而保证先到先得。
+#[derive(Debug)]
+struct TokenBucket {
+  rate: f64,
+  capacity: f64,
+  tokens: f64,
+  updated: Instant,
+}
+
+impl TokenBucket {
+  fn new(rate: f64, capacity: f64, now: Instant) -> Self {
+    Self {
+      rate,
+      capacity,
+      tokens: capacity,
+      updated: now,
+    }
+  }
+
+  /// 预订一个令牌，返回需要等待的时长；等待超过 `max_wait` 时不预订并返回 None
+  fn reserve(&mut self, now: Instant, max_wait: Duration) -> Option<Duration> {
+    let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+    self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+    self.updated = now;
+
+    let wait = if self.tokens >= 1.0 {
+      Duration::ZERO
+    } else {
+      Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+    };
+    if wait > max_wait {
+      return None;
+    }
+    self.tokens -= 1.0;
+    Some(wait)
+  }
+}
+
+/// 单个服务器的调用限制（并发上限 + 速率限制），随 `mcp_connect` 创建
+#[derive(Debug, Default)]
+pub struct CallLimiter {
+  semaphore: Option<Arc<Semaphore>>,
+  max_concurrent: usize,
+  bucket: Option<Mutex<TokenBucket>>,
+  max_wait: Option<Duration>,
+}
+
+/// 调用期间持有的许可，drop 时释放并发名额
+pub struct CallPermit {
+  _permit: Option<OwnedSemaphorePermit>,
+  /// 排队等待的时长
+  pub waited: Duration,
+}
+
+impl CallLimiter {
+  pub fn new(limits: Option<&McpCallLimits>) -> Self {
+    let Some(limits) = limits else {
+      return Self::default();
+    };
+    let max_concurrent = limits.max_concurrent.filter(|n| *n > 0);
+    let bucket = limits
+      .rate_per_second
+      .filter(|r| r.is_finite() && *r > 0.0)
+      .map(|rate| {
+        let burst = limits.burst.filter(|b| *b > 0).map(|b| b as f64);
+        Mutex::new(TokenBucket::new(
+          rate,
+          burst.unwrap_or_else(|| rate.ceil().max(1.0)),
+          Instant::now(),
+        ))
+      });
+    Self {
+      semaphore: max_concurrent.map(|n| Arc::new(Semaphore::new(n))),
+      max_concurrent: max_concurrent.unwrap_or(0),
+      bucket,
+      max_wait: limits.max_wait_ms.map(Duration::from_millis),
+    }
+  }
+
+  /// 先排队获取并发名额，再按速率限制等待；总等待超过上限时返回 `MCP_RATE_LIMITED` 错误。
+  /// `on_queued` 在确定需要等待时调用一次（参数为速率限制的预计等待时长，并发排队时为 None）
+  pub async fn acquire(
+    &self,
+    server_name: &str,
+    on_queued: impl FnOnce(Option<Duration>),
+  ) -> Result<CallPermit, String> {
+    let start = Instant::now();
+    let max_wait = self.max_wait.unwrap_or(DEFAULT_MAX_WAIT);
+    let mut on_queued = Some(on_queued);
+
+    let permit = match &self.semaphore {
+      Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+        Ok(p) => Some(p),
+        Err(_) => {
+          if let Some(f) = on_queued.take() {
+            f(None);
+          }
+          match tokio::time::timeout(max_wait, semaphore.clone().acquire_owned()).await {
+            Ok(Ok(p)) => Some(p),
+            Ok(Err(_)) => return Err(format!("{}: limiter closed", RATE_LIMITED)),
+            Err(_) => {
+              return Err(format!(
+                "{}: server {} already has {} calls in flight; waited {}ms",
+                RATE_LIMITED,
+                server_name,
+                self.max_concurrent,
+                max_wait.as_millis()
+              ))
+            }
+          }
+        }
+      },
+      None => None,
+    };
+
+    if let Some(bucket) = &self.bucket {
+      let remaining = max_wait.saturating_sub(start.elapsed());
+      let wait = bucket
+        .lock()
+        .map_err(|e| e.to_string())?
+        .reserve(Instant::now(), remaining);
+      match wait {
+        Some(wait) if wait.is_zero() => {}
+        Some(wait) => {
+          if let Some(f) = on_queued.take() {
+            f(Some(wait));
+          }
+          tokio::time::sleep(wait).await;
+        }
+        None => {
+          return Err(format!(
+            "{}: server {} exceeded its call rate limit; retry later",
+            RATE_LIMITED, server_name
+          ))
+        }
+      }
+    }
+
+    Ok(CallPermit {
+      _permit: permit,
+      waited: start.elapsed(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_token_bucket_reserves_in_order() {
+    let t0 = Instant::now();
+    let mut bucket = TokenBucket::new(2.0, 2.0, t0);
+    let max_wait = Duration::from_secs(1);
+    assert_eq!(bucket.reserve(t0, max_wait), Some(Duration::ZERO));
+    assert_eq!(bucket.reserve(t0, max_wait), Some(Duration::ZERO));
+    // 桶已空：第三、四个调用分别排队 0.5s、1s，第五个超过上限被拒绝且不占用令牌
+    assert_eq!(
+      bucket.reserve(t0, max_wait),
+      Some(Duration::from_millis(500))
+    );
+    assert_eq!(bucket.reserve(t0, max_wait), Some(Duration::from_secs(1)));
+    assert_eq!(bucket.reserve(t0, max_wait), None);
+    // 2 秒后补充 4 个令牌，抵消预订后剩 2 个
+    let t2 = t0 + Duration::from_secs(2);
+    assert_eq!(bucket.reserve(t2, max_wait), Some(Duration::ZERO));
+  }
+}
@@ -0,0 +1,612 @@
+use crate::document_parser::parse_document_text;
+use crate::mcp::state::McpState;
+use crate::onnx_logic::{
+  generate_embedding, run_embedding, tokenize_batch, EmbeddingInput, OnnxState,
+};
+use crate::web_search::commands::{
+  native_web_fetch, native_web_search, WebFetchRequest, WebSearchRequest,
+};
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rmcp::{
+  model::{
+    CallToolRequestParam, CallToolResult, Content, ErrorData as McpError, Implementation,
+    JsonObject, ListToolsResult, PaginatedRequestParam, ServerCapabilities, ServerInfo, Tool,
+  },
+  service::{RequestContext, RoleServer},
+  transport::sse_server::{SseServer, SseServerConfig},
+  ServerHandler,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+use tokio::net::TcpListener;
+use tokio::time::{timeout, Duration};
+use tokio_util::sync::CancellationToken;
+
+/// 访问令牌的持久化文件
+const TOKEN_STORE: &str = "mcp-local-server.json";
+const TOKEN_KEY: &str = "token";
+
+/// 知识库检索请求推送给前端的事件名
+pub const KB_QUERY_EVENT: &str = "mcp-local-kb-query";
+
+/// 等待前端完成知识库检索的最长时间
+const KB_QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+/// 停止时等待服务任务退出（释放监听端口）的上限，超时后强制结束
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 启动内置服务器时由前端传入的配置（与设置页中的搜索/嵌入配置保持一致）
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalServerOptions {
+  /// web_search / web_fetch 使用的提供商，缺省为 duckduckgo
+  pub search_provider: Option<String>,
+  pub search_api_key: Option<String>,
+  pub search_cse_id: Option<String>,
+  /// 本地嵌入模型的 tokenizer 路径；未设置时 generate_embeddings 不可用
+  pub tokenizer_path: Option<String>,
+  pub max_length: Option<usize>,
+  /// parse_document 可读取的目录；为空时该工具不可用
+  #[serde(default)]
+  pub document_dirs: Vec<String>,
+  /// 重新生成访问令牌（旧令牌立即失效）
+  #[serde(default)]
+  pub regenerate_token: bool,
+}
+
+/// 返回给前端展示的连接信息，外部客户端以 `Authorization: Bearer <token>` 访问
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalServerInfo {
+  pub url: String,
+  pub token: String,
+}
+
+/// 前端对知识库检索请求的答复（经 `mcp_respond_kb_query` 回填）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpKbQueryResponse {
+  pub results: Option<Value>,
+  pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct KbQueryEvent {
+  request_id: String,
+  query: String,
+  knowledge_base_id: Option<String>,
+  top_k: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchArgs {
+  query: String,
+  limit: Option<usize>,
+  site: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchArgs {
+  url: String,
+  max_content_chars: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParseArgs {
+  path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbedArgs {
+  texts: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KbArgs {
+  query: String,
+  knowledge_base_id: Option<String>,
+  top_k: Option<u32>,
+}
+
+/// 工具的运行环境
+#[derive(Clone)]
+enum Host {
+  /// 运行在应用内：嵌入使用应用已加载的模型，知识库检索转交前端
+  App(AppHandle),
+  /// 无界面模式（`chatless-mcp --stdio`）：自行持有嵌入模型，不提供知识库检索
+  Headless(Arc<OnnxState>),
+}
+
+/// 内置 MCP 服务：把应用自身的搜索、抓取、文档解析、嵌入与知识库检索暴露为工具
+#[derive(Clone)]
+pub struct LocalServer {
+  host: Host,
+  options: Arc<LocalServerOptions>,
+}
+
+fn tool(name: &'static str, description: &'static str, schema: Value) -> Tool {
+  let schema = match schema {
+    Value::Object(map) => map,
+    _ => JsonObject::new(),
+  };
+  Tool::new(name, description, Arc::new(schema))
+}
+
+fn tools(with_knowledge_base: bool) -> Vec<Tool> {
+  let mut tools = vec![
+    tool(
+      "web_search",
+      "Search the web with the search provider configured in Chatless",
+      json!({
+        "type": "object",
+        "properties": {
+          "query": { "type": "string", "description": "Search keywords" },
+          "limit": { "type": "integer", "description": "Maximum number of results" },
+          "site": { "type": "string", "description": "Restrict results to this site" }
+        },
+        "required": ["query"]
+      }),
+    ),
+    tool(
+      "web_fetch",
+      "Fetch a web page and return its title, readable text and links",
+      json!({
+        "type": "object",
+        "properties": {
+          "url": { "type": "string" },
+          "maxContentChars": { "type": "integer" }
+        },
+        "required": ["url"]
+      }),
+    ),
+    tool(
+      "parse_document",
+      "Extract plain text from a local document (pdf, docx, md, txt, ...) inside the directories shared by the user",
+      json!({
+        "type": "object",
+        "properties": {
+          "path": { "type": "string", "description": "Absolute file path inside a shared directory" }
+        },
+        "required": ["path"]
+      }),
+    ),
+    tool(
+      "generate_embeddings",
+      "Generate embeddings with the local embedding model",
+      json!({
+        "type": "object",
+        "properties": {
+          "texts": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["texts"]
+      }),
+    ),
+  ];
+  if with_knowledge_base {
+    tools.push(tool(
+      "knowledge_base_search",
+      "Retrieve relevant chunks from Chatless knowledge bases",
+      json!({
+        "type": "object",
+        "properties": {
+          "query": { "type": "string" },
+          "knowledgeBaseId": { "type": "string", "description": "Omit to search all knowledge bases" },
+          "topK": { "type": "integer" }
+        },
+        "required": ["query"]
+      }),
+    ));
+  }
+  tools
+}
+
+fn parse_args<T: DeserializeOwned>(args: Option<JsonObject>) -> Result<T, McpError> {
+  serde_json::from_value(Value::Object(args.unwrap_or_default()))
+    .map_err(|e| McpError::invalid_params(format!("Invalid arguments: {}", e), None))
+}
+
+fn to_result<T: Serialize>(result: Result<T, String>) -> CallToolResult {
+  match result.and_then(|v| serde_json::to_string_pretty(&v).map_err(|e| e.to_string())) {
+    Ok(text) => CallToolResult::success(vec![Content::text(text)]),
+    Err(e) => CallToolResult::error(vec![Content::text(e)]),
+  }
+}
+
+/// 规范化后的文件路径是否位于任一允许的目录内
+fn is_within(file: &Path, dirs: &[PathBuf]) -> bool {
+  dirs.iter().any(|dir| file.starts_with(dir))
+}
+
+impl LocalServer {
+  /// 无界面模式下的服务实例；onnx 中未加载模型时 generate_embeddings 返回错误
+  pub fn headless(options: LocalServerOptions, onnx: Arc<OnnxState>) -> Self {
+    Self {
+      host: Host::Headless(onnx),
+      options: Arc::new(options),
+    }
+  }
+
+  /// 只解析 `document_dirs` 内的文件；路径先规范化，`..` 与符号链接无法越界。
+  /// 不存在与越界返回同一错误，避免借此探测目录外的文件
+  async fn parse_document(&self, args: ParseArgs) -> Result<String, String> {
+    if self.options.document_dirs.is_empty() {
+      return Err("parse_document is disabled: no document directories are shared".to_string());
+    }
+    let mut dirs = Vec::new();
+    for dir in &self.options.document_dirs {
+      if let Ok(dir) = tokio::fs::canonicalize(dir).await {
+        dirs.push(dir);
+      }
+    }
+    match tokio::fs::canonicalize(&args.path).await {
+      Ok(file) if is_within(&file, &dirs) => {
+        parse_document_text(file.to_string_lossy().to_string()).await
+      }
+      _ => Err(format!(
+        "{} is not a file inside the directories shared for parse_document",
+        args.path
+      )),
+    }
+  }
+
+  async fn web_search(&self, args: SearchArgs) -> Result<Value, String> {
+    let o = &self.options;
+    let results = native_web_search(WebSearchRequest {
+      provider: o
+        .search_provider
+        .clone()
+        .unwrap_or_else(|| "duckduckgo".to_string()),
+      query: args.query,
+      api_key: o.search_api_key.clone(),
+      cse_id: o.search_cse_id.clone(),
+      limit: args.limit,
+      kl: None,
+      accept_language: None,
+      safe: None,
+      site: args.site,
+      max_results: args.limit.map(|l| l as i32),
+    })
+    .await?;
+    serde_json::to_value(results).map_err(|e| e.to_string())
+  }
+
+  async fn web_fetch(&self, args: FetchArgs) -> Result<Value, String> {
+    let o = &self.options;
+    let result = native_web_fetch(WebFetchRequest {
+      provider: o.search_provider.clone().unwrap_or_default(),
+      url: args.url,
+      api_key: o.search_api_key.clone(),
+      max_links: None,
+      max_content_chars: args.max_content_chars,
+      use_readability: None,
+    })
+    .await?;
+    serde_json::to_value(result).map_err(|e| e.to_string())
+  }
+
+  async fn embed(&self, args: EmbedArgs) -> Result<Vec<Vec<f32>>, String> {
+    let tokenizer_path = self
+      .options
+      .tokenizer_path
+      .clone()
+      .ok_or_else(|| "No local embedding model is configured in Chatless".to_string())?;
+    let tokens = tokenize_batch(
+      args.texts,
+      tokenizer_path,
+      self.options.max_length.unwrap_or(512),
+    )?;
+    let widen = |rows: Vec<Vec<u32>>| -> Vec<Vec<i64>> {
+      rows
+        .into_iter()
+        .map(|r| r.into_iter().map(i64::from).collect())
+        .collect()
+    };
+    let input = EmbeddingInput {
+      input_ids: widen(tokens.input_ids),
+      attention_mask: widen(tokens.attention_mask),
+      token_type_ids: widen(tokens.token_type_ids),
+    };
+    match &self.host {
+      Host::App(app) => generate_embedding(input, app.clone()).await,
+      Host::Headless(onnx) => {
+        let onnx = onnx.clone();
+        tokio::task::spawn_blocking(move || run_embedding(&onnx, input))
+          .await
+          .map_err(|e| e.to_string())?
+      }
+    }
+  }
+
+  /// 知识库由前端管理（数据库 + 向量检索），这里通过事件转交前端执行并等待回填
+  async fn knowledge_base_search(
+    &self,
+    args: KbArgs,
+    context: &RequestContext<RoleServer>,
+  ) -> Result<Value, String> {
+    let Host::App(app) = &self.host else {
+      return Err("Knowledge base search requires the Chatless app to be running".to_string());
+    };
+    let state = app.state::<McpState>();
+    let (request_id, rx) = state.kb_queries.register(());
+    let event = KbQueryEvent {
+      request_id: request_id.clone(),
+      query: args.query,
+      knowledge_base_id: args.knowledge_base_id,
+      top_k: args.top_k,
+    };
+    if let Err(e) = app.emit(KB_QUERY_EVENT, event) {
+      state.kb_queries.discard(&request_id);
+      return Err(format!("Failed to forward knowledge base query: {}", e));
+    }
+
+    let reply = tokio::select! {
+      r = timeout(KB_QUERY_TIMEOUT, rx) => r,
+      _ = context.ct.cancelled() => {
+        state.kb_queries.discard(&request_id);
+        return Err("Knowledge base query cancelled".to_string());
+      }
+    };
+    let reply = match reply {
+      Ok(Ok(reply)) => reply,
+      Ok(Err(_)) => return Err("Knowledge base query dropped".to_string()),
+      Err(_) => {
+        state.kb_queries.discard(&request_id);
+        log::warn!("[MCP/local] Knowledge base query {} timed out", request_id);
+        return Err("Knowledge base query timed out".to_string());
+      }
+    };
+    if let Some(err) = reply.error {
+      return Err(err);
+    }
+    Ok(reply.results.unwrap_or(Value::Array(vec![])))
+  }
+}
+
+impl ServerHandler for LocalServer {
+  fn get_info(&self) -> ServerInfo {
+    ServerInfo {
+      capabilities: ServerCapabilities::builder().enable_tools().build(),
+      server_info: Implementation {
+        name: "chatless".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        ..Default::default()
+      },
+      instructions: Some(
+        "Chatless built-in tools: web search/fetch, document parsing, embeddings and knowledge base retrieval"
+          .into(),
+      ),
+      ..Default::default()
+    }
+  }
+
+  async fn list_tools(
+    &self,
+    _request: Option<PaginatedRequestParam>,
+    _context: RequestContext<RoleServer>,
+  ) -> Result<ListToolsResult, McpError> {
+    Ok(ListToolsResult {
+      tools: tools(matches!(self.host, Host::App(_))),
+      ..Default::default()
+    })
+  }
+
+  async fn call_tool(
+    &self,
+    request: CallToolRequestParam,
+    context: RequestContext<RoleServer>,
+  ) -> Result<CallToolResult, McpError> {
+    log::info!("[MCP/local] Tool call: {}", request.name);
+    let args = request.arguments;
+    let result = match request.name.as_ref() {
+      "web_search" => to_result(self.web_search(parse_args(args)?).await),
+      "web_fetch" => to_result(self.web_fetch(parse_args(args)?).await),
+      "parse_document" => to_result(self.parse_document(parse_args(args)?).await),
+      "generate_embeddings" => to_result(self.embed(parse_args(args)?).await),
+      "knowledge_base_search" => to_result(
+        self
+          .knowledge_base_search(parse_args(args)?, &context)
+          .await,
+      ),
+      other => {
+        return Err(McpError::invalid_params(
+          format!("Unknown tool: {}", other),
+          None,
+        ))
+      }
+    };
+    Ok(result)
+  }
+}
+
+/// 读取（必要时生成）访问令牌
+fn load_token(app: &AppHandle, regenerate: bool) -> Result<String, String> {
+  let store = app
+    .store(TOKEN_STORE)
+    .map_err(|e| format!("Failed to open local server store: {}", e))?;
+  if !regenerate {
+    if let Some(token) = store
+      .get(TOKEN_KEY)
+      .and_then(|v| v.as_str().map(|s| s.to_string()))
+    {
+      return Ok(token);
+    }
+  }
+  let token = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+  store.set(TOKEN_KEY, Value::String(token.clone()));
+  store
+    .save()
+    .map_err(|e| format!("Failed to save local server store: {}", e))?;
+  Ok(token)
+}
+
+/// 只接受回环地址的 Host，防止 DNS rebinding 让网页脚本访问本地服务
+fn is_loopback_host(host: &str) -> bool {
+  let name = match host.strip_prefix('[') {
+    Some(rest) => rest.split(']').next().unwrap_or_default(),
+    None => host.split(':').next().unwrap_or_default(),
+  };
+  matches!(name, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// 以固定时间比较令牌：先取 SHA-256 摘要使长度一致，再逐字节异或累积，避免按前缀提前返回泄露时序
+fn token_matches(provided: &str, expected: &str) -> bool {
+  let a = Sha256::digest(provided.as_bytes());
+  let b = Sha256::digest(expected.as_bytes());
+  a.iter()
+    .zip(b.iter())
+    .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+    == 0
+}
+
+async fn require_token(State(token): State<Arc<String>>, req: Request, next: Next) -> Response {
+  let host_ok = req
+    .headers()
+    .get(header::HOST)
+    .and_then(|h| h.to_str().ok())
+    .is_some_and(is_loopback_host);
+  if !host_ok {
+    return (StatusCode::FORBIDDEN, "Invalid Host header").into_response();
+  }
+  let authorized = req
+    .headers()
+    .get(header::AUTHORIZATION)
+    .and_then(|h| h.to_str().ok())
+    .and_then(|h| h.strip_prefix("Bearer "))
+    .is_some_and(|t| token_matches(t, &token));
+  if !authorized {
+    return (StatusCode::UNAUTHORIZED, "Missing or invalid bearer token").into_response();
+  }
+  next.run(req).await
+}
+
+/// 运行中的内置服务器：取消令牌（取消即关闭监听与所有会话）及 HTTP 服务任务
+pub struct LocalServerHandle {
+  ct: CancellationToken,
+  task: JoinHandle<()>,
+}
+
+impl LocalServerHandle {
+  /// 通知服务器关闭，不等待其退出
+  pub fn cancel(&self) {
+    self.ct.cancel();
+  }
+
+  /// 关闭并等待服务任务结束，确保监听端口已释放（重启前调用）
+  pub async fn stop(self) {
+    self.ct.cancel();
+    let mut task = self.task;
+    if timeout(STOP_TIMEOUT, &mut task).await.is_err() {
+      log::warn!("[MCP/local] Server did not stop in time, aborting");
+      task.abort();
+      let _ = task.await;
+    }
+  }
+}
+
+/// 在回环地址上启动内置 MCP SSE 服务器
+pub(crate) async fn start(
+  app: AppHandle,
+  address: &str,
+  options: LocalServerOptions,
+) -> Result<(LocalServerHandle, LocalServerInfo), String> {
+  let bind: SocketAddr = address
+    .parse()
+    .map_err(|e| format!("invalid address {}: {}", address, e))?;
+  if !bind.ip().is_loopback() {
+    return Err(format!(
+      "The built-in MCP server only binds to loopback addresses, got {}",
+      bind.ip()
+    ));
+  }
+  let token = load_token(&app, options.regenerate_token)?;
+
+  let ct = CancellationToken::new();
+  let (sse, router) = SseServer::new(SseServerConfig {
+    bind,
+    sse_path: "/sse".to_string(),
+    post_path: "/message".to_string(),
+    ct: ct.clone(),
+    sse_keep_alive: None,
+  });
+  let router = router.layer(middleware::from_fn_with_state(
+    Arc::new(token.clone()),
+    require_token,
+  ));
+  let listener = TcpListener::bind(bind)
+    .await
+    .map_err(|e| format!("bind {} failed: {}", bind, e))?;
+
+  let shutdown = ct.child_token();
+  let task = tauri::async_runtime::spawn(async move {
+    let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+      shutdown.cancelled().await;
+    });
+    if let Err(e) = server.await {
+      log::error!("[MCP/local] Server stopped with error: {}", e);
+    }
+  });
+
+  let handler = LocalServer {
+    host: Host::App(app),
+    options: Arc::new(options),
+  };
+  let ct = sse.with_service(move || handler.clone());
+  log::info!("[MCP/local] Built-in MCP server listening on {}", bind);
+  Ok((
+    LocalServerHandle { ct, task },
+    LocalServerInfo {
+      url: format!("http://{}/sse", bind),
+      token,
+    },
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_loopback_host() {
+    assert!(is_loopback_host("127.0.0.1:8787"));
+    assert!(is_loopback_host("localhost"));
+    assert!(is_loopback_host("[::1]:8787"));
+    assert!(!is_loopback_host("evil.example.com:8787"));
+    assert!(!is_loopback_host("127.0.0.1.evil.example.com"));
+  }
+
+  #[test]
+  fn test_is_within_document_dirs() {
+    let dirs = vec![PathBuf::from("/home/u/docs")];
+    assert!(is_within(Path::new("/home/u/docs/a/report.pdf"), &dirs));
+    assert!(!is_within(
+      Path::new("/home/u/docs-private/report.pdf"),
+      &dirs
+    ));
+    assert!(!is_within(Path::new("/home/u/.ssh/id_ed25519"), &dirs));
+    assert!(!is_within(Path::new("/home/u/docs/a.pdf"), &[]));
+  }
+
+  #[test]
+  fn test_token_matches() {
+    assert!(token_matches("abc123", "abc123"));
+    assert!(!token_matches("abc12", "abc123"));
+    assert!(!token_matches("", "abc123"));
+  }
+}
@@ -1,3 +1,28 @@
+pub mod arguments;
+pub mod audit;
+pub mod auth;
+pub mod cache;
+pub mod calls;
 pub mod commands;
+pub mod connect;
+pub mod diagnose;
+pub mod elicitation;
+pub mod expand;
+pub mod handler;
+pub mod import;
+pub mod limits;
+pub mod local_server;
+pub mod pending;
+pub mod policy;
+pub mod registry;
+pub mod results;
+pub mod roots;
+pub mod sampling;
+pub mod sandbox;
+pub mod schema;
+pub mod shutdown;
 pub mod state;
+pub mod stderr;
+pub mod supervisor;
+pub mod tool_policy;
 pub mod types;
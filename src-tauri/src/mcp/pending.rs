@@ -0,0 +1,57 @@
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::oneshot;
+
+struct Waiter<T, C> {
+  tx: oneshot::Sender<T>,
+  context: C,
+}
+
+/// 等待前端回复的请求表：Rust 侧登记后通过事件把 requestId 发给前端，
+/// 前端再通过对应的命令携带 requestId 回填结果。
+/// `C` 为登记时附带的上下文（如需要校验回复的 schema），回填前可读取。
+pub struct PendingReplies<T, C = ()> {
+  prefix: &'static str,
+  seq: AtomicU64,
+  waiters: DashMap<String, Waiter<T, C>>,
+}
+
+impl<T, C: Clone> PendingReplies<T, C> {
+  pub fn new(prefix: &'static str) -> Self {
+    Self {
+      prefix,
+      seq: AtomicU64::new(1),
+      waiters: DashMap::new(),
+    }
+  }
+
+  /// 登记一个等待项，返回 requestId 与接收端
+  pub fn register(&self, context: C) -> (String, oneshot::Receiver<T>) {
+    let id = format!(
+      "{}-{}",
+      self.prefix,
+      self.seq.fetch_add(1, Ordering::SeqCst)
+    );
+    let (tx, rx) = oneshot::channel();
+    self.waiters.insert(id.clone(), Waiter { tx, context });
+    (id, rx)
+  }
+
+  /// 读取登记时附带的上下文；requestId 不存在时返回 None
+  pub fn context(&self, id: &str) -> Option<C> {
+    self.waiters.get(id).map(|w| w.context.clone())
+  }
+
+  /// 回填结果；requestId 不存在（已超时/已取消）时返回 false
+  pub fn resolve(&self, id: &str, value: T) -> bool {
+    match self.waiters.remove(id) {
+      Some((_, w)) => w.tx.send(value).is_ok(),
+      None => false,
+    }
+  }
+
+  /// 放弃等待（超时或服务器取消时调用）
+  pub fn discard(&self, id: &str) {
+    self.waiters.remove(id);
+  }
+}
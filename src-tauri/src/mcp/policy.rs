@@ -0,0 +1,410 @@
+use crate::mcp::types::McpServerConfig;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// 用户级策略文件名（位于应用配置目录）
+const POLICY_FILE: &str = "mcp-policy.json";
+/// 指定企业级策略文件路径的环境变量；存在企业策略时忽略用户策略
+const MANAGED_POLICY_ENV: &str = "CHATLESS_MCP_POLICY";
+/// 需要用户确认时 `mcp_connect` 返回的错误前缀，前端据此弹窗并以 confirmed=true 重试
+pub const CONFIRMATION_REQUIRED: &str = "MCP_CONFIRMATION_REQUIRED";
+/// 策略拒绝时的错误前缀
+pub const POLICY_DENIED: &str = "MCP_POLICY_DENIED";
+
+/// 单条可执行文件规则。`command` 与 `args` / `denyArgs` 中的模式支持 `*`、`?` 通配（不区分大小写）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommandRule {
+  pub command: String,
+  /// 为空表示不限制参数；否则每个参数都必须匹配其中之一
+  pub args: Vec<String>,
+  /// 命中任一模式的参数一律拒绝（优先于 args）
+  pub deny_args: Vec<String>,
+  pub require_confirmation: bool,
+}
+
+/// stdio MCP 服务器启动策略，示例：
+/// ```json
+/// {
+///   "commands": [
+///     { "command": "npx" }, { "command": "uvx" }, { "command": "bunx" },
+///     { "command": "docker", "args": ["run", "-i", "--rm", "-e", "*"], "denyArgs": ["--privileged", "-v", "--volume*"] },
+///     { "command": "python*", "requireConfirmation": true },
+///     { "command": "deno" }
+///   ],
+///   "envDeny": ["LD_PRELOAD", "DYLD_*"]
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StdioPolicy {
+  pub commands: Vec<CommandRule>,
+  /// 是否允许直接使用可执行文件路径（绝对或相对路径）
+  pub allow_paths: bool,
+  /// 是否允许 Windows 包装器 `cmd /c <cmd>`；被包装的命令仍按规则校验
+  pub allow_cmd_wrapper: bool,
+  /// 环境变量名白名单（为空表示不限制）
+  pub env_allow: Vec<String>,
+  /// 环境变量名黑名单（优先于白名单）
+  pub env_deny: Vec<String>,
+  /// 所有 stdio 服务器首次连接前均需用户确认
+  pub require_confirmation: bool,
+}
+
+impl Default for StdioPolicy {
+  /// 默认策略与历史行为一致：npx / uvx / bunx、显式路径与 `cmd /c`
+  fn default() -> Self {
+    Self {
+      commands: ["npx", "uvx", "bunx"]
+        .iter()
+        .map(|c| CommandRule {
+          command: c.to_string(),
+          ..Default::default()
+        })
+        .collect(),
+      allow_paths: true,
+      allow_cmd_wrapper: true,
+      env_allow: Vec::new(),
+      env_deny: Vec::new(),
+      require_confirmation: false,
+    }
+  }
+}
+
+/// 已加载的策略及其来源
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedPolicy {
+  pub policy: StdioPolicy,
+  /// 策略文件路径；使用内置默认策略时为空
+  pub source: Option<String>,
+  /// 是否为企业下发的策略（此时用户不可修改）
+  pub managed: bool,
+}
+
+/// 策略评估结果；reasons 列出全部拒绝原因，便于一次性展示给用户
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyDecision {
+  pub allowed: bool,
+  pub requires_confirmation: bool,
+  pub reasons: Vec<String>,
+}
+
+/// 企业策略路径：环境变量优先，其次为各平台的系统级配置目录
+fn managed_policy_path() -> Option<PathBuf> {
+  if let Ok(p) = std::env::var(MANAGED_POLICY_ENV) {
+    if !p.trim().is_empty() {
+      return Some(PathBuf::from(p));
+    }
+  }
+  let path = if cfg!(target_os = "windows") {
+    PathBuf::from(std::env::var("ProgramData").unwrap_or_else(|_| "C:\\ProgramData".into()))
+      .join("Chatless")
+      .join(POLICY_FILE)
+  } else if cfg!(target_os = "macos") {
+    PathBuf::from("/Library/Application Support/Chatless").join(POLICY_FILE)
+  } else {
+    PathBuf::from("/etc/chatless").join(POLICY_FILE)
+  };
+  path.exists().then_some(path)
+}
+
+pub(crate) fn user_policy_path(app: &AppHandle) -> Result<PathBuf, String> {
+  app
+    .path()
+    .app_config_dir()
+    .map(|d| d.join(POLICY_FILE))
+    .map_err(|e| format!("Failed to resolve config dir: {}", e))
+}
+
+fn read_policy(path: &Path) -> Result<StdioPolicy, String> {
+  let text = std::fs::read_to_string(path)
+    .map_err(|e| format!("Failed to read MCP policy {}: {}", path.display(), e))?;
+  serde_json::from_str(&text).map_err(|e| format!("Invalid MCP policy {}: {}", path.display(), e))
+}
+
+/// 加载生效的策略：企业策略 > 用户策略 > 内置默认。
+/// 策略文件存在但无法解析时返回错误（失败即拒绝，而不是静默回退到默认策略）。
+pub(crate) fn load(app: &AppHandle) -> Result<LoadedPolicy, String> {
+  if let Some(path) = managed_policy_path() {
+    return Ok(LoadedPolicy {
+      policy: read_policy(&path)?,
+      source: Some(path.display().to_string()),
+      managed: true,
+    });
+  }
+  let path = user_policy_path(app)?;
+  if path.exists() {
+    return Ok(LoadedPolicy {
+      policy: read_policy(&path)?,
+      source: Some(path.display().to_string()),
+      managed: false,
+    });
+  }
+  Ok(LoadedPolicy {
+    policy: StdioPolicy::default(),
+    source: None,
+    managed: false,
+  })
+}
+
+pub(crate) fn save_user_policy(app: &AppHandle, policy: &StdioPolicy) -> Result<(), String> {
+  if let Some(path) = managed_policy_path() {
+    return Err(format!(
+      "MCP policy is managed by {} and cannot be changed",
+      path.display()
+    ));
+  }
+  let path = user_policy_path(app)?;
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+  }
+  let text = serde_json::to_string_pretty(policy).map_err(|e| e.to_string())?;
+  std::fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// 简单通配匹配：`*` 任意长度，`?` 单个字符；ASCII 不区分大小写
+fn glob_match(pattern: &str, text: &str) -> bool {
+  let p: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+  let t: Vec<char> = text.to_ascii_lowercase().chars().collect();
+  let (mut pi, mut ti) = (0, 0);
+  let mut star: Option<(usize, usize)> = None;
+  while ti < t.len() {
+    if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+      pi += 1;
+      ti += 1;
+    } else if pi < p.len() && p[pi] == '*' {
+      star = Some((pi, ti));
+      pi += 1;
+    } else if let Some((sp, st)) = star {
+      pi = sp + 1;
+      ti = st + 1;
+      star = Some((sp, st + 1));
+    } else {
+      return false;
+    }
+  }
+  p[pi..].iter().all(|c| *c == '*')
+}
+
+pub(crate) fn matches_any(patterns: &[String], text: &str) -> bool {
+  patterns.iter().any(|p| glob_match(p, text))
+}
+
+/// 规则匹配时同时尝试原命令以及去掉 Windows 可执行扩展名后的形式。
+/// 带路径的命令只按完整路径匹配：`/tmp/x/npx` 不能借用 `npx` 的规则，只能由路径规则或 `allowPaths` 放行
+fn command_candidates(command: &str) -> Vec<String> {
+  let mut out = vec![command.to_string()];
+  let lower = command.to_ascii_lowercase();
+  for ext in [".exe", ".cmd", ".bat"] {
+    if lower.ends_with(ext) {
+      out.push(command[..command.len() - ext.len()].to_string());
+    }
+  }
+  out
+}
+
+/// 评估 stdio 配置是否符合策略（纯函数，不访问文件系统）
+pub fn evaluate(policy: &StdioPolicy, config: &McpServerConfig) -> PolicyDecision {
+  let mut reasons = Vec::new();
+  let mut requires_confirmation = policy.require_confirmation;
+
+  let Some(raw_command) = config.command.as_deref().filter(|c| !c.trim().is_empty()) else {
+    return PolicyDecision {
+      allowed: false,
+      requires_confirmation,
+      reasons: vec!["command required for stdio".to_string()],
+    };
+  };
+  let args: &[String] = config.args.as_deref().unwrap_or(&[]);
+
+  // Windows 包装器：cmd /c <cmd> <args...>，按被包装的命令校验
+  let is_wrapper = raw_command.eq_ignore_ascii_case("cmd")
+    && args
+      .first()
+      .map(|s| s.eq_ignore_ascii_case("/c"))
+      .unwrap_or(false)
+    && args.len() >= 2;
+  let (command, args) = if is_wrapper {
+    if !policy.allow_cmd_wrapper {
+      reasons.push("Windows wrapper 'cmd /c' is disabled by MCP policy".to_string());
+    }
+    (args[1].as_str(), &args[2..])
+  } else {
+    (raw_command, args)
+  };
+
+  let is_path = command.contains('/') || command.contains('\\');
+  let candidates = command_candidates(command);
+  let rule = policy
+    .commands
+    .iter()
+    .find(|r| candidates.iter().any(|c| glob_match(&r.command, c)));
+
+  match rule {
+    Some(rule) => {
+      requires_confirmation |= rule.require_confirmation;
+      for arg in args {
+        if matches_any(&rule.deny_args, arg) {
+          reasons.push(format!(
+            "argument '{}' is forbidden for '{}' by MCP policy",
+            arg, command
+          ));
+        } else if !rule.args.is_empty() && !matches_any(&rule.args, arg) {
+          reasons.push(format!(
+            "argument '{}' is not permitted for '{}' (allowed patterns: {})",
+            arg,
+            command,
+            rule.args.join(", ")
+          ));
+        }
+      }
+    }
+    None if is_path && policy.allow_paths => {}
+    None => {
+      let allowed: Vec<&str> = policy.commands.iter().map(|r| r.command.as_str()).collect();
+      let mut msg = format!(
+        "command '{}' is not allowed by MCP policy. Allowed commands: {}",
+        command,
+        if allowed.is_empty() {
+          "(none)".to_string()
+        } else {
+          allowed.join(", ")
+        }
+      );
+      if policy.allow_paths {
+        msg.push_str(", or an executable path");
+      } else if is_path {
+        msg.push_str(" (executable paths are disabled)");
+      }
+      reasons.push(msg);
+    }
+  }
+
+  for (key, _) in config.env.as_deref().unwrap_or(&[]) {
+    if matches_any(&policy.env_deny, key) {
+      reasons.push(format!(
+        "environment variable '{}' is denied by MCP policy",
+        key
+      ));
+    } else if !policy.env_allow.is_empty() && !matches_any(&policy.env_allow, key) {
+      reasons.push(format!(
+        "environment variable '{}' is not in the MCP policy allowlist",
+        key
+      ));
+    }
+  }
+
+  PolicyDecision {
+    allowed: reasons.is_empty(),
+    requires_confirmation,
+    reasons,
+  }
+}
+
+/// 加载策略并在展开后的配置上评估；策略文件损坏时同样视为拒绝。
+/// 拒绝原因中的参数按展开前的原文（`raw_args`）显示，避免密钥出现在日志和界面中
+pub(crate) fn check(
+  app: &AppHandle,
+  resolved: &McpServerConfig,
+  raw_args: &Option<Vec<String>>,
+) -> PolicyDecision {
+  match load(app) {
+    Ok(loaded) => {
+      let mut decision = evaluate(&loaded.policy, resolved);
+      let pairs = resolved
+        .args
+        .iter()
+        .flatten()
+        .zip(raw_args.iter().flatten());
+      for (expanded, raw) in pairs.filter(|(e, r)| e != r) {
+        let (expanded, raw) = (format!("'{}'", expanded), format!("'{}'", raw));
+        for reason in &mut decision.reasons {
+          *reason = reason.replace(&expanded, &raw);
+        }
+      }
+      decision
+    }
+    Err(e) => PolicyDecision {
+      allowed: false,
+      requires_confirmation: false,
+      reasons: vec![e],
+    },
+  }
+}
+
+/// 将拒绝结果格式化为返回给前端的错误文本
+pub(crate) fn denial_message(decision: &PolicyDecision) -> String {
+  format!("{}: {}", POLICY_DENIED, decision.reasons.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn stdio(command: &str, args: &[&str]) -> McpServerConfig {
+    serde_json::from_value(serde_json::json!({
+      "type": "stdio",
+      "command": command,
+      "args": args,
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn test_default_policy_matches_legacy_allowlist() {
+    let policy = StdioPolicy::default();
+    assert!(evaluate(&policy, &stdio("npx", &["-y", "pkg"])).allowed);
+    assert!(evaluate(&policy, &stdio("/usr/bin/server", &[])).allowed);
+    assert!(evaluate(&policy, &stdio("cmd", &["/c", "npx.cmd", "pkg"])).allowed);
+    let denied = evaluate(&policy, &stdio("docker", &["run"]));
+    assert!(!denied.allowed);
+    assert!(denied.reasons[0].contains("docker"));
+  }
+
+  #[test]
+  fn test_rule_argument_patterns_and_confirmation() {
+    let policy = StdioPolicy {
+      commands: vec![CommandRule {
+        command: "docker".into(),
+        args: vec!["run".into(), "-i".into(), "--rm".into(), "mcp/*".into()],
+        deny_args: vec!["--privileged".into()],
+        require_confirmation: true,
+      }],
+      ..Default::default()
+    };
+    let ok = evaluate(
+      &policy,
+      &stdio("docker", &["run", "-i", "--rm", "mcp/fetch"]),
+    );
+    assert!(ok.allowed && ok.requires_confirmation);
+    let bad = evaluate(
+      &policy,
+      &stdio("docker", &["run", "--privileged", "evil/img"]),
+    );
+    assert_eq!(bad.reasons.len(), 2);
+  }
+
+  #[test]
+  fn test_path_command_does_not_match_file_name_rule() {
+    let policy = StdioPolicy {
+      allow_paths: false,
+      ..Default::default()
+    };
+    let denied = evaluate(&policy, &stdio("/tmp/x/npx", &["-y", "pkg"]));
+    assert!(!denied.allowed);
+    assert!(denied.reasons[0].contains("executable paths are disabled"));
+    assert!(!evaluate(&policy, &stdio("C:\\tmp\\npx.cmd", &[])).allowed);
+    assert!(evaluate(&policy, &stdio("npx.cmd", &["pkg"])).allowed);
+  }
+
+  #[test]
+  fn test_glob_match() {
+    assert!(glob_match("python*", "python3.12"));
+    assert!(glob_match("DYLD_*", "dyld_insert_libraries"));
+    assert!(glob_match("?eno", "deno"));
+    assert!(!glob_match("deno", "denox"));
+  }
+}
@@ -0,0 +1,261 @@
+use crate::mcp::cache;
+use crate::mcp::policy::matches_any;
+use crate::mcp::state::McpState;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// 各服务器工具过滤设置的持久化文件（key 为服务器名）
+const FILTERS_STORE: &str = "mcp-tool-filters.json";
+/// 限定名中服务器前缀与工具名之间的分隔符
+pub const QUALIFIED_SEPARATOR: &str = "__";
+
+/// 单个服务器在聚合工具列表中的过滤设置。
+/// `allow` / `deny` 中的模式支持 `*`、`?` 通配（不区分大小写）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpToolFilter {
+  /// 关闭后该服务器的工具不出现在聚合列表中，也不能通过限定名调用
+  pub enabled: bool,
+  /// 为空表示不限制；否则只暴露匹配其中之一的工具
+  pub allow: Vec<String>,
+  /// 命中任一模式的工具一律隐藏（优先于 allow）
+  pub deny: Vec<String>,
+}
+
+impl Default for McpToolFilter {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      allow: Vec::new(),
+      deny: Vec::new(),
+    }
+  }
+}
+
+impl McpToolFilter {
+  pub fn permits(&self, tool_name: &str) -> bool {
+    self.enabled
+      && !matches_any(&self.deny, tool_name)
+      && (self.allow.is_empty() || matches_any(&self.allow, tool_name))
+  }
+}
+
+/// 聚合列表中的一项：限定名在所有已连接服务器之间唯一
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpQualifiedTool {
+  pub qualified_name: String,
+  pub server_name: String,
+  pub tool: Tool,
+}
+
+pub(crate) fn load_filters(app: &AppHandle) -> HashMap<String, McpToolFilter> {
+  let store = match app.store(FILTERS_STORE) {
+    Ok(s) => s,
+    Err(e) => {
+      log::warn!("[MCP/registry] Failed to open tool filter store: {}", e);
+      return HashMap::new();
+    }
+  };
+  store
+    .entries()
+    .into_iter()
+    .filter_map(|(name, v)| Some((name, serde_json::from_value(v).ok()?)))
+    .collect()
+}
+
+pub(crate) fn save_filter(
+  app: &AppHandle,
+  server_name: &str,
+  filter: &McpToolFilter,
+) -> Result<(), String> {
+  let store = app
+    .store(FILTERS_STORE)
+    .map_err(|e| format!("Failed to open tool filter store: {}", e))?;
+  let value = serde_json::to_value(filter).map_err(|e| e.to_string())?;
+  store.set(server_name, value);
+  store
+    .save()
+    .map_err(|e| format!("Failed to save tool filter store: {}", e))
+}
+
+/// 服务器名 → 限定名前缀，只取决于名称本身，同一限定名不会随其他服务器的连接与断开改指别的服务器。
+/// 前缀只含 `[A-Za-z0-9_-]`（满足多数模型对函数名的要求）；名称中没有可用字符时以名称的摘要区分。
+/// 前缀不含 `__`，因此限定名在第一个分隔符处即可无歧义地拆分。
+pub(crate) fn server_prefix(name: &str) -> String {
+  // 连续下划线合并、去掉首尾下划线，保证前缀中不含分隔符 `__`
+  let mut prefix = String::new();
+  for c in name.chars() {
+    if c.is_ascii_alphanumeric() || c == '-' {
+      prefix.push(c);
+    } else if !prefix.is_empty() && !prefix.ends_with('_') {
+      prefix.push('_');
+    }
+  }
+  while prefix.ends_with('_') {
+    prefix.pop();
+  }
+  if prefix.is_empty() {
+    let digest = Sha256::digest(name.as_bytes());
+    prefix = format!(
+      "server_{:02x}{:02x}{:02x}{:02x}",
+      digest[0], digest[1], digest[2], digest[3]
+    );
+  }
+  prefix
+}
+
+/// 找出前缀对应的服务器；多个服务器规范化为同一前缀时返回错误，而不是任选其一
+fn owner_of(prefix: &str, server_names: &[String]) -> Result<String, String> {
+  let owners: Vec<&String> = server_names
+    .iter()
+    .filter(|name| server_prefix(name) == prefix)
+    .collect();
+  match owners.as_slice() {
+    [] => Err(format!("No connected server for prefix '{}'", prefix)),
+    [owner] => Ok(owner.to_string()),
+    _ => {
+      let mut names: Vec<&str> = owners.iter().map(|n| n.as_str()).collect();
+      names.sort();
+      Err(format!(
+        "Ambiguous prefix '{}': servers {} normalize to the same prefix; rename one of them",
+        prefix,
+        names.join(", ")
+      ))
+    }
+  }
+}
+
+pub(crate) fn qualified_name(prefix: &str, tool_name: &str) -> String {
+  format!("{}{}{}", prefix, QUALIFIED_SEPARATOR, tool_name)
+}
+
+/// 汇总所有已连接且启用的服务器的工具（应用过滤设置）。
+/// 单个服务器列举失败只记录日志，不影响其他服务器。
+pub(crate) async fn list_all(
+  app: &AppHandle,
+  state: &McpState,
+  force_refresh: bool,
+) -> Vec<McpQualifiedTool> {
+  let filters = load_filters(app);
+  let mut server_names: Vec<String> = state.services.iter().map(|e| e.key().clone()).collect();
+  server_names.sort();
+
+  let mut all = Vec::new();
+  for server_name in &server_names {
+    let filter = filters.get(server_name).cloned().unwrap_or_default();
+    if !filter.enabled {
+      continue;
+    }
+    let prefix = server_prefix(server_name);
+    // 前缀冲突的服务器不出现在聚合列表中，避免同一限定名指向不同服务器
+    if let Err(e) = owner_of(&prefix, &server_names) {
+      log::warn!("[MCP/registry] Skipping server {}: {}", server_name, e);
+      continue;
+    }
+    let tools = match cache::tools(state, server_name, force_refresh).await {
+      Ok(tools) => tools,
+      Err(e) => {
+        log::warn!(
+          "[MCP/registry] Skipping server {} in aggregated listing: {}",
+          server_name,
+          e
+        );
+        continue;
+      }
+    };
+    all.extend(
+      tools
+        .into_iter()
+        .filter(|t| filter.permits(&t.name))
+        .map(|tool| McpQualifiedTool {
+          qualified_name: qualified_name(&prefix, &tool.name),
+          server_name: server_name.clone(),
+          tool,
+        }),
+    );
+  }
+  all
+}
+
+/// 工具被过滤设置隐藏（或服务器被关闭）时返回错误；直接调用与按限定名调用共用
+pub(crate) fn check_filter(
+  app: &AppHandle,
+  server_name: &str,
+  tool_name: &str,
+) -> Result<(), String> {
+  let filter = load_filters(app).remove(server_name).unwrap_or_default();
+  if filter.permits(tool_name) {
+    Ok(())
+  } else {
+    Err(format!(
+      "Tool {} on server {} is disabled by the tool filter",
+      tool_name, server_name
+    ))
+  }
+}
+
+/// 将限定名解析为 (服务器名, 工具名)：按分隔符拆出前缀，只在所属服务器的工具列表中查找
+pub(crate) async fn resolve(state: &McpState, qualified: &str) -> Result<(String, String), String> {
+  let (prefix, tool_name) = qualified
+    .split_once(QUALIFIED_SEPARATOR)
+    .ok_or_else(|| format!("Invalid qualified tool name: {}", qualified))?;
+  let server_names: Vec<String> = state.services.iter().map(|e| e.key().clone()).collect();
+  let server_name = owner_of(prefix, &server_names)?;
+  let tools = cache::tools(state, &server_name, false).await?;
+  if !tools.iter().any(|t| t.name == tool_name) {
+    return Err(format!("Tool not found: {}", qualified));
+  }
+  Ok((server_name, tool_name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_server_prefix_depends_only_on_the_name() {
+    assert_eq!(server_prefix("github"), "github");
+    assert_eq!(server_prefix("my server"), "my_server");
+    assert_eq!(server_prefix("a__b!"), "a_b");
+    let cjk = server_prefix("数据库");
+    assert!(cjk.starts_with("server_") && !cjk.contains("__"));
+    assert_ne!(cjk, server_prefix("文件"));
+    assert_eq!(qualified_name("github", "search"), "github__search");
+  }
+
+  #[test]
+  fn test_owner_of_rejects_ambiguous_prefixes() {
+    let names: Vec<String> = ["a b", "a_b", "github"]
+      .iter()
+      .map(|s| s.to_string())
+      .collect();
+    assert_eq!(owner_of("github", &names).unwrap(), "github");
+    let err = owner_of("a_b", &names).unwrap_err();
+    assert!(err.contains("Ambiguous prefix") && err.contains("a b, a_b"));
+    // 冲突的服务器断开后，前缀才指向剩下的那个
+    assert_eq!(owner_of("a_b", &names[1..]).unwrap(), "a_b");
+    assert!(owner_of("missing", &names).is_err());
+  }
+
+  #[test]
+  fn test_filter_deny_overrides_allow() {
+    let filter = McpToolFilter {
+      allow: vec!["read_*".into(), "list_*".into()],
+      deny: vec!["read_secret*".into()],
+      ..Default::default()
+    };
+    assert!(filter.permits("read_file"));
+    assert!(!filter.permits("read_secrets"));
+    assert!(!filter.permits("write_file"));
+    let disabled = McpToolFilter {
+      enabled: false,
+      ..Default::default()
+    };
+    assert!(!disabled.permits("read_file"));
+  }
+}
@@ -0,0 +1,313 @@
+use crate::mcp::cache;
+use crate::mcp::schema;
+use crate::mcp::state::McpState;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rmcp::model::CallToolResult;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+/// 二进制内容与完整长文本的缓存目录（位于应用缓存目录下）
+const CACHE_DIR: &str = "mcp-results";
+/// 单个文本块超过该字符数时截断，剩余部分通过 `mcp_read_result_text` 分段读取
+const MAX_TEXT_CHARS: usize = 32_000;
+/// 缓存文件的保留时间
+const CACHE_TTL: Duration = Duration::from_secs(3 * 24 * 3600);
+
+static PRUNED: AtomicBool = AtomicBool::new(false);
+
+/// `mcp_read_result_text` 返回的一段文本
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpTextChunk {
+  pub text: String,
+  pub offset: usize,
+  /// 还有剩余内容时为下一段的起始位置
+  pub next_offset: Option<usize>,
+  pub total_chars: usize,
+}
+
+fn cache_dir_path(app: &AppHandle) -> Result<PathBuf, String> {
+  Ok(
+    app
+      .path()
+      .app_cache_dir()
+      .map_err(|e| format!("Failed to resolve cache dir: {}", e))?
+      .join(CACHE_DIR),
+  )
+}
+
+async fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  let dir = cache_dir_path(app)?;
+  tokio::fs::create_dir_all(&dir)
+    .await
+    .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+  if !PRUNED.swap(true, Ordering::SeqCst) {
+    let prune_dir = dir.clone();
+    let _ = tokio::task::spawn_blocking(move || prune(&prune_dir)).await;
+  }
+  Ok(dir)
+}
+
+/// 每次启动后首次写缓存时清理过期文件
+fn prune(dir: &Path) {
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return;
+  };
+  let now = SystemTime::now();
+  for entry in entries.flatten() {
+    let expired = entry
+      .metadata()
+      .and_then(|m| m.modified())
+      .ok()
+      .and_then(|t| now.duration_since(t).ok())
+      .is_some_and(|age| age > CACHE_TTL);
+    if expired {
+      let _ = std::fs::remove_file(entry.path());
+    }
+  }
+}
+
+fn extension_for(mime: &str) -> &'static str {
+  match mime.split(';').next().unwrap_or_default().trim() {
+    "image/png" => "png",
+    "image/jpeg" | "image/jpg" => "jpg",
+    "image/gif" => "gif",
+    "image/webp" => "webp",
+    "image/svg+xml" => "svg",
+    "audio/wav" | "audio/x-wav" => "wav",
+    "audio/mpeg" | "audio/mp3" => "mp3",
+    "audio/ogg" => "ogg",
+    "application/pdf" => "pdf",
+    "application/json" => "json",
+    m if m.starts_with("text/") => "txt",
+    _ => "bin",
+  }
+}
+
+/// 按内容哈希写入缓存（相同内容只保存一份），返回文件路径
+fn write_cached(dir: &Path, bytes: &[u8], ext: &str) -> Result<PathBuf, String> {
+  let digest: String = Sha256::digest(bytes)
+    .iter()
+    .map(|b| format!("{:02x}", b))
+    .collect();
+  let name = format!("{}.{}", digest, ext);
+  let path = dir.join(name);
+  if !path.exists() {
+    std::fs::write(&path, bytes)
+      .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+  }
+  Ok(path)
+}
+
+/// 把 base64 字段替换为缓存文件引用；解码或写入失败时保留原样
+fn externalize(dir: &Path, obj: &mut Map<String, Value>, data_key: &str) {
+  let Some(Value::String(data)) = obj.get(data_key) else {
+    return;
+  };
+  let bytes = match STANDARD.decode(data.trim()) {
+    Ok(b) => b,
+    Err(e) => {
+      log::warn!("[MCP/results] Invalid base64 in {}: {}", data_key, e);
+      return;
+    }
+  };
+  let mime = obj
+    .get("mimeType")
+    .and_then(Value::as_str)
+    .unwrap_or("application/octet-stream");
+  match write_cached(dir, &bytes, extension_for(mime)) {
+    Ok(path) => {
+      obj.remove(data_key);
+      obj.insert("file".into(), json!(path.to_string_lossy()));
+      obj.insert("size".into(), json!(bytes.len()));
+    }
+    Err(e) => log::warn!("[MCP/results] {}", e),
+  }
+}
+
+/// 超长文本只保留开头部分，完整内容写入缓存，附带用于续读的 handle
+fn truncate_text(dir: &Path, obj: &mut Map<String, Value>) {
+  let Some(Value::String(text)) = obj.get("text") else {
+    return;
+  };
+  let total = text.chars().count();
+  if total <= MAX_TEXT_CHARS {
+    return;
+  }
+  let path = match write_cached(dir, text.as_bytes(), "txt") {
+    Ok(p) => p,
+    Err(e) => {
+      log::warn!("[MCP/results] {}", e);
+      return;
+    }
+  };
+  let handle = path
+    .file_stem()
+    .map(|s| s.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let head: String = text.chars().take(MAX_TEXT_CHARS).collect();
+  obj.insert("text".into(), Value::String(head));
+  obj.insert(
+    "truncated".into(),
+    json!({
+      "handle": handle,
+      "nextOffset": MAX_TEXT_CHARS,
+      "totalChars": total,
+    }),
+  );
+}
+
+fn process_block(dir: &Path, block: &mut Value) {
+  let Some(obj) = block.as_object_mut() else {
+    return;
+  };
+  match obj.get("type").and_then(Value::as_str) {
+    Some("image") | Some("audio") => externalize(dir, obj, "data"),
+    Some("text") => truncate_text(dir, obj),
+    Some("resource") => {
+      if let Some(resource) = obj.get_mut("resource").and_then(Value::as_object_mut) {
+        externalize(dir, resource, "blob");
+        truncate_text(dir, resource);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// 结果后处理：
+/// - image / audio / 嵌入资源的 base64 内容写入缓存目录，只回传文件路径、大小与 MIME
+/// - 超长文本截断并附带续读 handle
+/// - 工具声明了 outputSchema 时校验 structuredContent，问题记录在 `outputSchemaErrors`
+///   （structuredContent 本身不做截断）
+pub(crate) async fn process(
+  app: &AppHandle,
+  state: &McpState,
+  server_name: &str,
+  tool_name: &str,
+  result: CallToolResult,
+) -> Result<Value, String> {
+  let mut value = serde_json::to_value(&result).map_err(|e| e.to_string())?;
+  let dir = cache_dir(app).await?;
+  // 解码与写文件都是阻塞操作，放到阻塞线程池中执行，避免占用异步运行时
+  value = tokio::task::spawn_blocking(move || {
+    if let Some(Value::Array(blocks)) = value.get_mut("content") {
+      for block in blocks {
+        process_block(&dir, block);
+      }
+    }
+    value
+  })
+  .await
+  .map_err(|e| format!("Failed to process tool result: {}", e))?;
+
+  let output_schema = cache::tools(state, server_name, false)
+    .await
+    .ok()
+    .and_then(|tools| tools.into_iter().find(|t| t.name == tool_name))
+    .and_then(|t| t.output_schema);
+  if let (Some(output_schema), Some(obj)) = (output_schema, value.as_object_mut()) {
+    let is_error = obj.get("isError").and_then(Value::as_bool).unwrap_or(false);
+    let errors = match obj.get("structuredContent") {
+      Some(structured) => schema::validate(&Value::Object((*output_schema).clone()), structured),
+      None if !is_error => vec![schema::SchemaError {
+        path: String::new(),
+        message: "tool declares an outputSchema but returned no structuredContent".into(),
+      }],
+      None => Vec::new(),
+    };
+    if !errors.is_empty() {
+      log::warn!(
+        "[MCP/results] Tool {} on {} violated its outputSchema: {}",
+        tool_name,
+        server_name,
+        schema::describe(&errors)
+      );
+      obj.insert("outputSchemaErrors".into(), json!(errors));
+    }
+  }
+  Ok(value)
+}
+
+/// 读取被截断文本的后续内容
+pub(crate) async fn read_text(
+  app: &AppHandle,
+  handle: &str,
+  offset: usize,
+  limit: Option<usize>,
+) -> Result<McpTextChunk, String> {
+  // handle 为内容哈希，拒绝其他字符以防路径穿越
+  if handle.is_empty() || !handle.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(format!("Invalid result handle: {}", handle));
+  }
+  let path = cache_dir_path(app)?.join(format!("{}.txt", handle));
+  let text = tokio::fs::read_to_string(&path)
+    .await
+    .map_err(|_| format!("Result text {} not found (expired?)", handle))?;
+  Ok(slice_chars(&text, offset, limit.unwrap_or(MAX_TEXT_CHARS)))
+}
+
+fn slice_chars(text: &str, offset: usize, limit: usize) -> McpTextChunk {
+  let total_chars = text.chars().count();
+  let chunk: String = text.chars().skip(offset).take(limit).collect();
+  let end = offset.saturating_add(limit);
+  McpTextChunk {
+    text: chunk,
+    offset,
+    next_offset: (end < total_chars).then_some(end),
+    total_chars,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_binary_blocks_are_externalized_and_text_truncated() {
+    // 每次运行使用独立目录，结束后删除（断言失败时也会清理）
+    struct TempDir(PathBuf);
+    impl Drop for TempDir {
+      fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+      }
+    }
+    let nanos = SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .map(|d| d.as_nanos())
+      .unwrap_or(0);
+    let temp = TempDir(std::env::temp_dir().join(format!(
+      "chatless-mcp-results-test-{}-{}",
+      std::process::id(),
+      nanos
+    )));
+    let dir = temp.0.clone();
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut image =
+      json!({ "type": "image", "data": STANDARD.encode(b"png-bytes"), "mimeType": "image/png" });
+    process_block(&dir, &mut image);
+    assert!(image.get("data").is_none());
+    assert_eq!(image["size"], json!(9));
+    let file = image["file"].as_str().unwrap();
+    assert!(file.ends_with(".png"));
+    assert_eq!(std::fs::read(file).unwrap(), b"png-bytes");
+
+    let long = "字".repeat(MAX_TEXT_CHARS + 10);
+    let mut text = json!({ "type": "text", "text": long });
+    process_block(&dir, &mut text);
+    assert_eq!(
+      text["text"].as_str().unwrap().chars().count(),
+      MAX_TEXT_CHARS
+    );
+    assert_eq!(text["truncated"]["totalChars"], json!(MAX_TEXT_CHARS + 10));
+
+    let chunk = slice_chars(&long, MAX_TEXT_CHARS, MAX_TEXT_CHARS);
+    assert_eq!(chunk.text.chars().count(), 10);
+    assert_eq!(chunk.next_offset, None);
+  }
+}
@@ -0,0 +1,88 @@
+use reqwest::Url;
+use rmcp::model::Root;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// 各服务器已授权 roots 的持久化文件（key 为服务器名）
+const ROOTS_STORE: &str = "mcp-roots.json";
+
+/// 读取某服务器已授权的 roots；读取失败时按“无授权目录”处理
+pub(crate) fn load_roots(app: &AppHandle, server_name: &str) -> Vec<Root> {
+  let store = match app.store(ROOTS_STORE) {
+    Ok(s) => s,
+    Err(e) => {
+      log::warn!("[MCP/roots] Failed to open roots store: {}", e);
+      return Vec::new();
+    }
+  };
+  store
+    .get(server_name)
+    .and_then(|v| serde_json::from_value::<Vec<Root>>(v).ok())
+    .unwrap_or_default()
+}
+
+pub(crate) fn save_roots(app: &AppHandle, server_name: &str, roots: &[Root]) -> Result<(), String> {
+  let store = app
+    .store(ROOTS_STORE)
+    .map_err(|e| format!("Failed to open roots store: {}", e))?;
+  let value = serde_json::to_value(roots).map_err(|e| e.to_string())?;
+  store.set(server_name, value);
+  store
+    .save()
+    .map_err(|e| format!("Failed to save roots store: {}", e))
+}
+
+/// 将用户输入（本地路径或 file:// URI）规范化为 MCP Root：
+/// 必须是已存在的目录，统一转换为绝对路径的 file:// URI。
+pub(crate) fn normalize_root(path_or_uri: &str, name: Option<String>) -> Result<Root, String> {
+  let path: PathBuf = if path_or_uri.starts_with("file://") {
+    Url::parse(path_or_uri)
+      .ok()
+      .and_then(|u| u.to_file_path().ok())
+      .ok_or_else(|| format!("Invalid file URI: {}", path_or_uri))?
+  } else {
+    PathBuf::from(path_or_uri)
+  };
+
+  let canonical = std::fs::canonicalize(&path)
+    .map_err(|e| format!("Root directory does not exist: {} ({})", path.display(), e))?;
+  if !canonical.is_dir() {
+    return Err(format!("Root is not a directory: {}", canonical.display()));
+  }
+
+  let uri = Url::from_file_path(&canonical)
+    .map_err(|_| format!("Cannot convert to file URI: {}", canonical.display()))?;
+  let name = name
+    .filter(|n| !n.trim().is_empty())
+    .or_else(|| default_name(&canonical));
+  Ok(Root {
+    uri: uri.to_string(),
+    name,
+  })
+}
+
+fn default_name(path: &Path) -> Option<String> {
+  path.file_name().map(|n| n.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_normalize_root_accepts_path_and_uri() {
+    let dir = std::env::temp_dir();
+    let from_path = normalize_root(&dir.to_string_lossy(), None).unwrap();
+    assert!(from_path.uri.starts_with("file://"));
+
+    let from_uri = normalize_root(&from_path.uri, Some("tmp".into())).unwrap();
+    assert_eq!(from_uri.uri, from_path.uri);
+    assert_eq!(from_uri.name.as_deref(), Some("tmp"));
+  }
+
+  #[test]
+  fn test_normalize_root_rejects_missing_dir() {
+    assert!(normalize_root("/definitely/not/a/real/dir/chatless", None).is_err());
+  }
+}
@@ -0,0 +1,100 @@
+use crate::mcp::state::McpState;
+use rmcp::model::{
+  CreateMessageRequestParam, CreateMessageResult, ErrorCode, ErrorData as McpError,
+};
+use rmcp::service::{RequestContext, RoleClient};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::time::{timeout, Duration};
+
+/// 服务器请求 LLM 采样时推送给前端的事件名
+pub const SAMPLING_REQUEST_EVENT: &str = "mcp-sampling-request";
+
+/// 等待用户审批 + 模型生成的最长时间
+const SAMPLING_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SamplingRequestEvent {
+  request_id: String,
+  server_name: String,
+  params: CreateMessageRequestParam,
+}
+
+/// 前端对采样请求的答复（经 `mcp_respond_sampling` 回填）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpSamplingResponse {
+  /// 用户是否批准本次采样；未批准时不会把任何内容返回给服务器
+  pub approved: bool,
+  /// 批准后由前端模型网关生成的结果
+  pub result: Option<CreateMessageResult>,
+  /// 生成失败时的错误信息
+  pub error: Option<String>,
+}
+
+/// 将 `sampling/createMessage` 转交前端处理，并在返回前强制检查用户审批结果
+pub(crate) async fn request_completion(
+  app: &AppHandle,
+  server_name: &str,
+  params: CreateMessageRequestParam,
+  context: RequestContext<RoleClient>,
+) -> Result<CreateMessageResult, McpError> {
+  let state = app.state::<McpState>();
+  let (request_id, rx) = state.sampling.register(());
+  log::info!(
+    "[MCP/sampling] Server {} requested a completion ({} messages), requestId={}",
+    server_name,
+    params.messages.len(),
+    request_id
+  );
+
+  let event = SamplingRequestEvent {
+    request_id: request_id.clone(),
+    server_name: server_name.to_string(),
+    params,
+  };
+  if let Err(e) = app.emit(SAMPLING_REQUEST_EVENT, event) {
+    state.sampling.discard(&request_id);
+    return Err(McpError::internal_error(
+      format!("Failed to forward sampling request: {}", e),
+      None,
+    ));
+  }
+
+  let reply = tokio::select! {
+    r = timeout(SAMPLING_TIMEOUT, rx) => r,
+    _ = context.ct.cancelled() => {
+      log::info!("[MCP/sampling] Request {} cancelled by server", request_id);
+      state.sampling.discard(&request_id);
+      return Err(McpError::internal_error("Sampling request cancelled", None));
+    }
+  };
+  let reply = match reply {
+    Ok(Ok(reply)) => reply,
+    Ok(Err(_)) => {
+      return Err(McpError::internal_error("Sampling request dropped", None));
+    }
+    Err(_) => {
+      state.sampling.discard(&request_id);
+      log::warn!("[MCP/sampling] Request {} timed out", request_id);
+      return Err(McpError::internal_error("Sampling request timed out", None));
+    }
+  };
+
+  if !reply.approved {
+    log::info!("[MCP/sampling] Request {} rejected by user", request_id);
+    // 规范建议：用户拒绝时返回 -1
+    return Err(McpError::new(
+      ErrorCode(-1),
+      "User rejected sampling request",
+      None,
+    ));
+  }
+  if let Some(err) = reply.error {
+    return Err(McpError::internal_error(err, None));
+  }
+  reply
+    .result
+    .ok_or_else(|| McpError::internal_error("Sampling approved but no result was provided", None))
+}
@@ -0,0 +1,423 @@
+use crate::mcp::roots;
+use crate::mcp::types::{McpSandboxConfig, McpServerConfig};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tokio::process::Command;
+
+/// 沙箱启动计划：bubblewrap 参数 + 资源限制
+#[derive(Debug, Clone)]
+pub(crate) struct SandboxPlan {
+  bwrap: PathBuf,
+  args: Vec<String>,
+  limits: McpSandboxConfig,
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+  let path = std::env::var_os("PATH")?;
+  std::env::split_paths(&path)
+    .map(|dir| dir.join(program))
+    .find(|candidate| candidate.is_file())
+}
+
+/// 可写目录：已授权的 roots + 配置中额外声明的路径（需存在，统一为绝对路径）
+fn writable_dirs(app: &AppHandle, name: &str, sandbox: &McpSandboxConfig) -> Vec<PathBuf> {
+  let mut dirs: Vec<PathBuf> = roots::load_roots(app, name)
+    .iter()
+    .filter_map(|r| reqwest::Url::parse(&r.uri).ok())
+    .filter_map(|u| u.to_file_path().ok())
+    .collect();
+  dirs.extend(sandbox.writable_paths.iter().map(|p| expand_home(p)));
+  canonical_dirs(dirs, "writable")
+}
+
+/// RLIMIT_NPROC 统计的是该用户的全部任务（进程与线程），并非仅沙箱内的进程；
+/// 上限不超过当前数量时 bwrap 自身就无法 fork，这里提前给出明确的错误
+#[cfg(target_os = "linux")]
+fn check_process_limit(sandbox: &McpSandboxConfig) -> Result<(), String> {
+  let Some(max) = sandbox.max_processes else {
+    return Ok(());
+  };
+  let current = user_task_count();
+  if max <= current {
+    return Err(format!(
+      "sandbox.maxProcesses ({}) must be greater than the number of processes and threads your user already runs ({}): the limit applies to all of the user's processes, not just the sandbox",
+      max, current
+    ));
+  }
+  Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_process_limit(_sandbox: &McpSandboxConfig) -> Result<(), String> {
+  Ok(())
+}
+
+/// 当前用户拥有的任务数（/proc/<pid>/task 下的线程合计）
+#[cfg(target_os = "linux")]
+fn user_task_count() -> u64 {
+  use std::os::unix::fs::MetadataExt;
+  // SAFETY: getuid 总是成功且无副作用
+  let uid = unsafe { libc::getuid() };
+  let Ok(entries) = std::fs::read_dir("/proc") else {
+    return 0;
+  };
+  entries
+    .flatten()
+    .filter(|e| e.file_name().to_string_lossy().parse::<u32>().is_ok())
+    .filter(|e| e.metadata().map(|m| m.uid() == uid).unwrap_or(false))
+    .map(|e| {
+      std::fs::read_dir(e.path().join("task"))
+        .map(|tasks| tasks.count() as u64)
+        .unwrap_or(1)
+    })
+    .sum()
+}
+
+/// 只读挂载的系统目录（不存在的跳过，符号链接按原样重建）
+const SYSTEM_DIRS: [&str; 10] = [
+  "/usr",
+  "/bin",
+  "/sbin",
+  "/lib",
+  "/lib32",
+  "/lib64",
+  "/libx32",
+  "/etc",
+  "/opt",
+  // 部分发行版的 /etc/resolv.conf 指向这里
+  "/run/systemd/resolve",
+];
+/// /etc 下可能含凭据的文件，以空文件遮盖
+const MASKED_FILES: [&str; 3] = ["/etc/shadow", "/etc/gshadow", "/etc/sudoers"];
+/// /etc 下可能含凭据的目录，以空 tmpfs 遮盖
+const MASKED_DIRS: [&str; 2] = ["/etc/ssh", "/etc/ssl/private"];
+/// 沙箱内进程可见的环境变量（配置中的 env 另行追加）
+const SANDBOX_ENV: [&str; 2] = ["PATH", "HOME"];
+
+/// 沙箱内的文件系统布局，由 `plan` 按当前环境收集，`bwrap_args` 据此生成参数
+#[derive(Debug, Default)]
+struct Layout {
+  system: Vec<PathBuf>,
+  /// (链接目标, 链接路径)，如 merged-usr 发行版的 /bin -> usr/bin
+  symlinks: Vec<(PathBuf, PathBuf)>,
+  masked_files: Vec<PathBuf>,
+  masked_dirs: Vec<PathBuf>,
+  /// 主目录整体替换为空 tmpfs
+  home: Option<PathBuf>,
+  /// 只读可见：工作目录、主目录下的可执行文件安装目录与 readablePaths
+  readable: Vec<PathBuf>,
+  /// 可写：已授权的 roots 与 writablePaths
+  writable: Vec<PathBuf>,
+  /// 始终隐藏（本应用的数据、配置与缓存目录，含密钥和令牌），在可写目录之后挂载以覆盖它们
+  hidden: Vec<PathBuf>,
+}
+
+fn path_arg(path: &Path) -> String {
+  path.to_string_lossy().to_string()
+}
+
+/// 按布局生成 bubblewrap 参数（不含程序本身）
+fn bwrap_args(layout: &Layout, allow_network: bool) -> Vec<String> {
+  let mut args: Vec<String> = Vec::new();
+  let mut push = |flag: &str, paths: &[&Path]| {
+    args.push(flag.to_string());
+    args.extend(paths.iter().map(|p| path_arg(p)));
+  };
+  for dir in &layout.system {
+    push("--ro-bind", &[dir, dir]);
+  }
+  for (target, link) in &layout.symlinks {
+    push("--symlink", &[target, link]);
+  }
+  for file in &layout.masked_files {
+    push("--ro-bind", &[Path::new("/dev/null"), file]);
+  }
+  for dir in &layout.masked_dirs {
+    push("--tmpfs", &[dir]);
+  }
+  push("--dev", &[Path::new("/dev")]);
+  push("--proc", &[Path::new("/proc")]);
+  push("--tmpfs", &[Path::new("/tmp")]);
+  if let Some(home) = &layout.home {
+    push("--tmpfs", &[home]);
+  }
+  for dir in &layout.readable {
+    push("--ro-bind", &[dir, dir]);
+  }
+  for dir in &layout.writable {
+    push("--bind", &[dir, dir]);
+  }
+  for dir in &layout.hidden {
+    push("--tmpfs", &[dir]);
+  }
+  args.extend(
+    [
+      "--unshare-pid",
+      "--unshare-ipc",
+      "--unshare-uts",
+      "--new-session",
+      "--die-with-parent",
+    ]
+    .iter()
+    .map(|s| s.to_string()),
+  );
+  if !allow_network {
+    args.push("--unshare-net".to_string());
+  }
+  args.push("--".to_string());
+  args
+}
+
+fn expand_home(path: &str) -> PathBuf {
+  match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+    (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+    _ => PathBuf::from(path),
+  }
+}
+
+fn canonical_dirs(dirs: impl IntoIterator<Item = PathBuf>, kind: &str) -> Vec<PathBuf> {
+  dirs
+    .into_iter()
+    .filter_map(|d| match std::fs::canonicalize(&d) {
+      Ok(c) => Some(c),
+      Err(e) => {
+        log::warn!(
+          "[MCP/sandbox] Skipping {} path {}: {}",
+          kind,
+          d.display(),
+          e
+        );
+        None
+      }
+    })
+    .collect()
+}
+
+/// 可执行文件位于主目录下（如 nvm 安装的 node）时需要只读挂载其安装目录：
+/// 位于 `bin` 目录时取其上一级，否则取所在目录
+fn install_dir(program: &str, home: &Path) -> Option<PathBuf> {
+  let exe = if program.contains('/') {
+    PathBuf::from(program)
+  } else {
+    find_in_path(program)?
+  };
+  let exe = std::fs::canonicalize(&exe).unwrap_or(exe);
+  if !exe.starts_with(home) {
+    return None;
+  }
+  let parent = exe.parent()?;
+  match parent.file_name() {
+    Some(n) if n == "bin" => parent.parent().map(Path::to_path_buf),
+    _ => Some(parent.to_path_buf()),
+  }
+}
+
+fn collect_layout(app: &AppHandle, name: &str, config: &McpServerConfig) -> Layout {
+  let sandbox = config.sandbox.clone().unwrap_or_default();
+  let mut layout = Layout::default();
+  for dir in SYSTEM_DIRS.iter().map(Path::new) {
+    match std::fs::read_link(dir) {
+      Ok(target) => layout.symlinks.push((target, dir.to_path_buf())),
+      Err(_) if dir.is_dir() => layout.system.push(dir.to_path_buf()),
+      Err(_) => {}
+    }
+  }
+  layout.masked_files = MASKED_FILES
+    .iter()
+    .map(PathBuf::from)
+    .filter(|p| p.is_file())
+    .collect();
+  layout.masked_dirs = MASKED_DIRS
+    .iter()
+    .map(PathBuf::from)
+    .filter(|p| p.is_dir())
+    .collect();
+  layout.home = std::env::var_os("HOME")
+    .map(PathBuf::from)
+    .and_then(|h| std::fs::canonicalize(h).ok());
+
+  let mut readable: Vec<PathBuf> = config.cwd.iter().map(PathBuf::from).collect();
+  if let (Some(home), Some(command)) = (&layout.home, &config.command) {
+    readable.extend(install_dir(command, home));
+  }
+  readable.extend(sandbox.readable_paths.iter().map(|p| expand_home(p)));
+  layout.readable = canonical_dirs(readable, "readable");
+  layout.writable = writable_dirs(app, name, &sandbox);
+
+  let paths = app.path();
+  layout.hidden = [
+    paths.app_data_dir(),
+    paths.app_config_dir(),
+    paths.app_local_data_dir(),
+    paths.app_cache_dir(),
+  ]
+  .into_iter()
+  .flatten()
+  .filter_map(|d| std::fs::canonicalize(d).ok())
+  .collect();
+  layout.hidden.sort();
+  layout.hidden.dedup();
+  layout
+}
+
+/// 根据配置生成沙箱计划；未启用时返回 None。
+/// 启用但当前平台不支持或缺少 bubblewrap 时直接报错，绝不静默降级为无沙箱运行。
+pub(crate) fn plan(
+  app: &AppHandle,
+  name: &str,
+  config: &McpServerConfig,
+) -> Result<Option<SandboxPlan>, String> {
+  let Some(sandbox) = config.sandbox.as_ref().filter(|s| s.enabled) else {
+    return Ok(None);
+  };
+  if !cfg!(target_os = "linux") {
+    return Err("Sandbox mode is only supported on Linux".to_string());
+  }
+  let bwrap = find_in_path("bwrap").ok_or_else(|| {
+    "Sandbox mode requires bubblewrap (bwrap); please install it, e.g. `apt install bubblewrap`"
+      .to_string()
+  })?;
+  check_process_limit(sandbox)?;
+
+  let args = bwrap_args(&collect_layout(app, name, config), sandbox.allow_network);
+  log::info!(
+    "[MCP/sandbox] Server {} will run in bubblewrap (network: {})",
+    name,
+    if sandbox.allow_network {
+      "allowed"
+    } else {
+      "denied"
+    }
+  );
+  Ok(Some(SandboxPlan {
+    bwrap,
+    args,
+    limits: sandbox.clone(),
+  }))
+}
+
+impl SandboxPlan {
+  /// 构造在沙箱中运行 `program` 的命令；调用方随后照常追加参数与环境变量。
+  /// 不继承应用的环境变量，仅保留 PATH 与 HOME（HOME 在沙箱内是空的 tmpfs）
+  pub(crate) fn command(&self, program: &str) -> Command {
+    let mut c = Command::new(&self.bwrap);
+    c.env_clear();
+    for key in SANDBOX_ENV {
+      if let Some(v) = std::env::var_os(key) {
+        c.env(key, v);
+      }
+    }
+    c.args(&self.args);
+    c.arg(program);
+    self.apply_limits(&mut c);
+    c
+  }
+
+  #[cfg(target_os = "linux")]
+  fn apply_limits(&self, c: &mut Command) {
+    let limits: Vec<_> = [
+      (
+        libc::RLIMIT_AS,
+        self
+          .limits
+          .memory_limit_mb
+          .map(|m| m.saturating_mul(1024 * 1024)),
+      ),
+      (libc::RLIMIT_CPU, self.limits.cpu_time_secs),
+      (libc::RLIMIT_NPROC, self.limits.max_processes),
+      (libc::RLIMIT_NOFILE, self.limits.max_open_files),
+    ]
+    .into_iter()
+    .filter_map(|(resource, value)| value.map(|v| (resource, v)))
+    .collect();
+    if limits.is_empty() {
+      return;
+    }
+    // SAFETY: pre_exec 闭包在 fork 后、exec 前执行，这里只调用异步信号安全的 setrlimit
+    unsafe {
+      c.pre_exec(move || {
+        for (resource, value) in &limits {
+          let rlim = libc::rlimit {
+            rlim_cur: *value as libc::rlim_t,
+            rlim_max: *value as libc::rlim_t,
+          };
+          if libc::setrlimit(*resource, &rlim) != 0 {
+            return Err(std::io::Error::last_os_error());
+          }
+        }
+        Ok(())
+      });
+    }
+  }
+
+  #[cfg(not(target_os = "linux"))]
+  fn apply_limits(&self, _c: &mut Command) {}
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn layout() -> Layout {
+    Layout {
+      system: vec!["/usr".into(), "/etc".into()],
+      symlinks: vec![("usr/bin".into(), "/bin".into())],
+      masked_files: vec!["/etc/shadow".into()],
+      home: Some("/home/u".into()),
+      readable: vec!["/home/u/.nvm/versions/node/v20".into()],
+      writable: vec!["/home/u/project".into(), "/home/u/.npm".into()],
+      hidden: vec!["/home/u/.local/share/chatless".into()],
+      ..Default::default()
+    }
+  }
+
+  fn position(args: &[String], window: &[&str]) -> Option<usize> {
+    args.windows(window.len()).position(|w| w == window)
+  }
+
+  #[test]
+  fn test_bwrap_args_bind_only_system_dirs_and_hide_home() {
+    let args = bwrap_args(&layout(), false);
+    assert!(position(&args, &["--ro-bind", "/", "/"]).is_none());
+    assert!(position(&args, &["--ro-bind", "/usr", "/usr"]).is_some());
+    assert!(position(&args, &["--symlink", "usr/bin", "/bin"]).is_some());
+    assert!(position(&args, &["--ro-bind", "/dev/null", "/etc/shadow"]).is_some());
+    assert!(args.contains(&"--new-session".to_string()));
+
+    // 主目录先被替换为 tmpfs，再挂载可读/可写目录，最后遮盖应用数据目录
+    let home = position(&args, &["--tmpfs", "/home/u"]).unwrap();
+    let readable = position(
+      &args,
+      &[
+        "--ro-bind",
+        "/home/u/.nvm/versions/node/v20",
+        "/home/u/.nvm/versions/node/v20",
+      ],
+    )
+    .unwrap();
+    let writable = position(&args, &["--bind", "/home/u/.npm", "/home/u/.npm"]).unwrap();
+    let hidden = position(&args, &["--tmpfs", "/home/u/.local/share/chatless"]).unwrap();
+    assert!(home < readable && readable < writable && writable < hidden);
+    assert_eq!(args.last().map(String::as_str), Some("--"));
+  }
+
+  #[test]
+  fn test_writable_paths_expand_home_and_skip_missing() {
+    if let Some(home) = std::env::var_os("HOME") {
+      assert_eq!(expand_home("~/.npm"), PathBuf::from(home).join(".npm"));
+    }
+    assert_eq!(expand_home("/srv/data"), PathBuf::from("/srv/data"));
+    let tmp = std::env::temp_dir();
+    let dirs = canonical_dirs(
+      [tmp.clone(), tmp.join("chatless-sandbox-missing-dir")],
+      "writable",
+    );
+    assert_eq!(dirs, vec![std::fs::canonicalize(&tmp).unwrap()]);
+  }
+
+  #[test]
+  fn test_bwrap_args_network_is_denied_unless_allowed() {
+    assert!(bwrap_args(&layout(), false).contains(&"--unshare-net".to_string()));
+    assert!(!bwrap_args(&layout(), true).contains(&"--unshare-net".to_string()));
+  }
+}
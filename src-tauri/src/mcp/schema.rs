@@ -0,0 +1,412 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// 单条校验错误：path 为 JSON Pointer 风格的位置（根为空串）
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaError {
+  pub path: String,
+  pub message: String,
+}
+
+/// 轻量 JSON Schema 校验，覆盖 MCP 工具 inputSchema 与 elicitation requestedSchema 的常用子集：
+/// type / enum / const / properties / required / additionalProperties / items /
+/// 数值与长度范围 / anyOf / oneOf / allOf。未识别的关键字一律忽略（宽松处理）。
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaError> {
+  let mut errors = Vec::new();
+  validate_at(schema, value, "", &mut errors);
+  errors
+}
+
+fn push(errors: &mut Vec<SchemaError>, path: &str, message: String) {
+  errors.push(SchemaError {
+    path: path.to_string(),
+    message,
+  });
+}
+
+/// 值的 JSON Schema 类型名；整数同时满足 "integer" 与 "number"
+pub(crate) fn type_name(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "boolean",
+    Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+    Value::Number(_) => "number",
+    Value::String(_) => "string",
+    Value::Array(_) => "array",
+    Value::Object(_) => "object",
+  }
+}
+
+pub(crate) fn matches_type(expected: &str, value: &Value) -> bool {
+  let actual = type_name(value);
+  actual == expected
+    || (expected == "number" && actual == "integer")
+    || (expected == "integer" && value.as_f64().map(|f| f.fract() == 0.0).unwrap_or(false))
+}
+
+/// schema 中声明的类型列表（`type` 可为字符串或数组）
+pub(crate) fn declared_types(schema: &Map<String, Value>) -> Vec<&str> {
+  match schema.get("type") {
+    Some(Value::String(t)) => vec![t.as_str()],
+    Some(Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+    _ => Vec::new(),
+  }
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<SchemaError>) {
+  let Some(schema) = schema.as_object() else {
+    // `true` / 缺省 schema 接受任意值；`false` 拒绝一切
+    if schema == &Value::Bool(false) {
+      push(errors, path, "no value is allowed here".into());
+    }
+    return;
+  };
+
+  let types = declared_types(schema);
+  if !types.is_empty() && !types.iter().any(|t| matches_type(t, value)) {
+    push(
+      errors,
+      path,
+      format!("expected {}, got {}", types.join(" | "), type_name(value)),
+    );
+    return;
+  }
+
+  if let Some(Value::Array(options)) = schema.get("enum") {
+    if !options.contains(value) {
+      push(
+        errors,
+        path,
+        format!("must be one of {}", Value::Array(options.clone())),
+      );
+    }
+  }
+  if let Some(c) = schema.get("const") {
+    if c != value {
+      push(errors, path, format!("must equal {}", c));
+    }
+  }
+
+  match value {
+    Value::Number(n) => {
+      let v = n.as_f64().unwrap_or(0.0);
+      if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+        if v < min {
+          push(errors, path, format!("must be >= {}", min));
+        }
+      }
+      if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+        if v > max {
+          push(errors, path, format!("must be <= {}", max));
+        }
+      }
+      if let Some(min) = schema.get("exclusiveMinimum").and_then(Value::as_f64) {
+        if v <= min {
+          push(errors, path, format!("must be > {}", min));
+        }
+      }
+      if let Some(max) = schema.get("exclusiveMaximum").and_then(Value::as_f64) {
+        if v >= max {
+          push(errors, path, format!("must be < {}", max));
+        }
+      }
+    }
+    Value::String(s) => {
+      let len = s.chars().count() as u64;
+      if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if len < min {
+          push(errors, path, format!("must be at least {} characters", min));
+        }
+      }
+      if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if len > max {
+          push(errors, path, format!("must be at most {} characters", max));
+        }
+      }
+    }
+    Value::Array(items) => {
+      let len = items.len() as u64;
+      if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if len < min {
+          push(errors, path, format!("must contain at least {} items", min));
+        }
+      }
+      if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if len > max {
+          push(errors, path, format!("must contain at most {} items", max));
+        }
+      }
+      if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate() {
+          validate_at(item_schema, item, &format!("{}/{}", path, i), errors);
+        }
+      }
+    }
+    Value::Object(obj) => validate_object(schema, obj, path, errors),
+    _ => {}
+  }
+
+  if let Some(Value::Array(all)) = schema.get("allOf") {
+    for sub in all {
+      validate_at(sub, value, path, errors);
+    }
+  }
+  for key in ["anyOf", "oneOf"] {
+    if let Some(Value::Array(branches)) = schema.get(key) {
+      let matched = branches
+        .iter()
+        .filter(|b| validate(b, value).is_empty())
+        .count();
+      let ok = if key == "oneOf" {
+        matched == 1
+      } else {
+        matched >= 1
+      };
+      if !ok {
+        push(errors, path, format!("does not match {}", key));
+      }
+    }
+  }
+}
+
+fn validate_object(
+  schema: &Map<String, Value>,
+  obj: &Map<String, Value>,
+  path: &str,
+  errors: &mut Vec<SchemaError>,
+) {
+  let properties = schema.get("properties").and_then(Value::as_object);
+
+  if let Some(Value::Array(required)) = schema.get("required") {
+    for key in required.iter().filter_map(Value::as_str) {
+      if !obj.contains_key(key) {
+        push(
+          errors,
+          &format!("{}/{}", path, key),
+          "required property is missing".into(),
+        );
+      }
+    }
+  }
+
+  for (key, v) in obj {
+    let child_path = format!("{}/{}", path, key);
+    match properties.and_then(|p| p.get(key)) {
+      Some(prop_schema) => validate_at(prop_schema, v, &child_path, errors),
+      None => match schema.get("additionalProperties") {
+        Some(Value::Bool(false)) => {
+          push(errors, &child_path, "unexpected property".into());
+        }
+        Some(extra @ Value::Object(_)) => validate_at(extra, v, &child_path, errors),
+        _ => {}
+      },
+    }
+  }
+}
+
+/// 将错误列表格式化为一行文本（用于日志或简单提示）
+pub fn describe(errors: &[SchemaError]) -> String {
+  errors
+    .iter()
+    .map(|e| {
+      if e.path.is_empty() {
+        e.message.clone()
+      } else {
+        format!("{}: {}", e.path, e.message)
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("; ")
+}
+
+/// 在校验前修正模型输出中常见的类型偏差，返回已应用的修正说明（用于日志）。只做无歧义的转换：
+/// - schema 不接受字符串时，把 `"42"`、`"true"`、`"[1,2]"` 之类的字符串转换为对应类型
+/// - 可选属性为 null 且 schema 不接受 null 时移除该属性
+/// - 缺失的可选属性若声明了 `default` 则填入默认值
+pub fn coerce(schema: &Value, value: &mut Value) -> Vec<String> {
+  let mut applied = Vec::new();
+  coerce_at(schema, value, "", &mut applied);
+  applied
+}
+
+fn coerce_string(s: &str, types: &[&str]) -> Option<Value> {
+  let t = s.trim();
+  types.iter().find_map(|ty| match *ty {
+    "integer" => t.parse::<i64>().ok().map(Value::from),
+    "number" => t.parse::<i64>().ok().map(Value::from).or_else(|| {
+      t.parse::<f64>()
+        .ok()
+        .filter(|f| f.is_finite())
+        .map(Value::from)
+    }),
+    "boolean" if t.eq_ignore_ascii_case("true") => Some(Value::Bool(true)),
+    "boolean" if t.eq_ignore_ascii_case("false") => Some(Value::Bool(false)),
+    "array" | "object" => serde_json::from_str::<Value>(t)
+      .ok()
+      .filter(|v| type_name(v) == *ty),
+    _ => None,
+  })
+}
+
+fn coerce_at(schema: &Value, value: &mut Value, path: &str, applied: &mut Vec<String>) {
+  let Some(schema) = schema.as_object() else {
+    return;
+  };
+  let types = declared_types(schema);
+  if let Value::String(s) = value {
+    if !types.is_empty() && !types.contains(&"string") {
+      if let Some(converted) = coerce_string(s, &types) {
+        applied.push(format!(
+          "{}: string converted to {}",
+          display_path(path),
+          type_name(&converted)
+        ));
+        *value = converted;
+      }
+    }
+  }
+
+  match value {
+    Value::Object(obj) => {
+      let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+      };
+      let required: Vec<&str> = match schema.get("required") {
+        Some(Value::Array(r)) => r.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+      };
+      for (key, prop_schema) in properties {
+        let child_path = format!("{}/{}", path, key);
+        let optional = !required.contains(&key.as_str());
+        let accepts_null = prop_schema.as_object().is_none_or(|p| {
+          let t = declared_types(p);
+          t.is_empty() || t.contains(&"null")
+        });
+        match obj.get(key) {
+          Some(Value::Null) if optional && !accepts_null => {
+            obj.remove(key);
+            applied.push(format!("{}: null optional property removed", child_path));
+          }
+          Some(_) => {
+            if let Some(v) = obj.get_mut(key) {
+              coerce_at(prop_schema, v, &child_path, applied);
+            }
+          }
+          None if optional => {
+            if let Some(default) = prop_schema.get("default") {
+              obj.insert(key.clone(), default.clone());
+              applied.push(format!("{}: default value applied", child_path));
+            }
+          }
+          None => {}
+        }
+      }
+    }
+    Value::Array(items) => {
+      if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter_mut().enumerate() {
+          coerce_at(item_schema, item, &format!("{}/{}", path, i), applied);
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+fn display_path(path: &str) -> &str {
+  if path.is_empty() {
+    "(root)"
+  } else {
+    path
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn schema() -> Value {
+    json!({
+      "type": "object",
+      "properties": {
+        "name": { "type": "string", "minLength": 1 },
+        "age": { "type": "integer", "minimum": 0 },
+        "color": { "type": "string", "enum": ["red", "green"] }
+      },
+      "required": ["name"],
+      "additionalProperties": false
+    })
+  }
+
+  #[test]
+  fn test_valid_object_passes() {
+    let errors = validate(&schema(), &json!({ "name": "a", "age": 3, "color": "red" }));
+    assert!(errors.is_empty(), "{:?}", errors);
+  }
+
+  #[test]
+  fn test_reports_each_violation_with_path() {
+    let errors = validate(
+      &schema(),
+      &json!({ "age": -1, "color": "blue", "extra": 1 }),
+    );
+    let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+    assert!(paths.contains(&"/name"));
+    assert!(paths.contains(&"/age"));
+    assert!(paths.contains(&"/color"));
+    assert!(paths.contains(&"/extra"));
+  }
+
+  #[test]
+  fn test_integer_accepted_as_number() {
+    assert!(validate(&json!({ "type": "number" }), &json!(3)).is_empty());
+    assert!(!validate(&json!({ "type": "integer" }), &json!(3.5)).is_empty());
+  }
+
+  #[test]
+  fn test_coerce_fixes_common_model_mistakes() {
+    let schema = json!({
+      "type": "object",
+      "properties": {
+        "count": { "type": "integer" },
+        "ratio": { "type": "number" },
+        "flag": { "type": "boolean" },
+        "tags": { "type": "array", "items": { "type": "integer" } },
+        "note": { "type": "string" },
+        "mode": { "type": "string", "default": "fast" },
+        "limit": { "type": "integer" }
+      },
+      "required": ["count"]
+    });
+    let mut value = json!({
+      "count": "3",
+      "ratio": " 0.5 ",
+      "flag": "TRUE",
+      "tags": "[1, \"2\"]",
+      "note": "42",
+      "limit": null
+    });
+    let applied = coerce(&schema, &mut value);
+    assert_eq!(
+      value,
+      json!({
+        "count": 3,
+        "ratio": 0.5,
+        "flag": true,
+        "tags": [1, 2],
+        "note": "42",
+        "mode": "fast"
+      })
+    );
+    assert_eq!(applied.len(), 7, "{:?}", applied);
+    assert!(validate(&schema, &value).is_empty());
+
+    // 无法无歧义转换的值保持原样，交给校验报错
+    let mut bad = json!({ "count": "three" });
+    assert_eq!(coerce(&schema, &mut bad).len(), 1);
+    assert_eq!(bad["count"], json!("three"));
+    assert!(!validate(&schema, &bad).is_empty());
+  }
+}
@@ -0,0 +1,79 @@
+use crate::mcp::state::McpState;
+use futures_util::future::join_all;
+use tokio::time::{timeout, Duration};
+
+/// 等待各服务正常关闭的上限，超时后直接结束剩余的子进程组
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 退出应用前关闭全部 MCP 连接：
+/// 1. 清空监管信息，避免监控任务在关闭过程中触发重连
+/// 2. 取消进行中的工具调用
+/// 3. 并发 cancel 所有服务并限时等待
+/// 4. 向仍存活的 stdio 子进程组发送 SIGKILL（npx 拉起的 node 等孙进程一并结束）
+pub async fn shutdown_all(state: &McpState) {
+  state.servers.clear();
+
+  let call_ids: Vec<String> = state
+    .pending_calls
+    .iter()
+    .map(|e| e.key().clone())
+    .collect();
+  for call_id in call_ids {
+    if let Some((_, call)) = state.pending_calls.remove(&call_id) {
+      call.cancel("application exiting".to_string());
+    }
+  }
+
+  let names: Vec<String> = state.services.iter().map(|e| e.key().clone()).collect();
+  let services: Vec<_> = names
+    .into_iter()
+    .filter_map(|name| state.services.remove(&name))
+    .collect();
+  if !services.is_empty() {
+    log::info!("[MCP/shutdown] Stopping {} MCP server(s)", services.len());
+    let cancels = join_all(services.into_iter().map(|(name, service)| async move {
+      if let Err(e) = service.cancel().await {
+        log::warn!("[MCP/shutdown] Failed to stop server {}: {}", name, e);
+      }
+    }));
+    if timeout(SHUTDOWN_TIMEOUT, cancels).await.is_err() {
+      log::warn!(
+        "[MCP/shutdown] Servers did not stop within {}s, killing remaining processes",
+        SHUTDOWN_TIMEOUT.as_secs()
+      );
+    }
+  }
+
+  kill_process_groups(state);
+}
+
+/// stdio 子进程以独立进程组启动（pgid 即 pid），按组结束可连带清理其派生的进程。
+/// 传输关闭、启动失败或断开时会移除对应记录，这里只剩仍在运行的服务的进程组
+#[cfg(unix)]
+fn kill_process_groups(state: &McpState) {
+  let groups: Vec<(String, u32)> = state
+    .child_pids
+    .iter()
+    .map(|e| (e.key().clone(), *e.value()))
+    .collect();
+  state.child_pids.clear();
+  for (name, pid) in groups {
+    let Ok(pgid) = libc::pid_t::try_from(pid) else {
+      continue;
+    };
+    // SAFETY: killpg 仅发送信号；进程组已不存在时返回 ESRCH，忽略即可
+    if unsafe { libc::killpg(pgid, libc::SIGKILL) } == 0 {
+      log::info!(
+        "[MCP/shutdown] Killed process group {} of server {}",
+        pgid,
+        name
+      );
+    }
+  }
+}
+
+/// 其他平台依赖 rmcp 在传输关闭时结束子进程
+#[cfg(not(unix))]
+fn kill_process_groups(state: &McpState) {
+  state.child_pids.clear();
+}
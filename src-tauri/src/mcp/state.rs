@@ -1,14 +1,91 @@
+use crate::mcp::cache::ServerListings;
+use crate::mcp::calls::PendingCall;
+use crate::mcp::elicitation::McpElicitationResponse;
+use crate::mcp::handler::McpClientHandler;
+use crate::mcp::limits::CallLimiter;
+use crate::mcp::local_server::McpKbQueryResponse;
+use crate::mcp::pending::PendingReplies;
+use crate::mcp::sampling::McpSamplingResponse;
+use crate::mcp::stderr::StderrLog;
+use crate::mcp::types::{McpServerConfig, McpServerStatus};
 use dashmap::DashMap;
-use rmcp::service::{RoleClient, RunningService};
+use rmcp::model::{InitializeResult, ProgressToken};
+use rmcp::service::{Peer, RoleClient, RunningService};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
 use std::sync::Arc;
+use tokio::sync::OnceCell;
 
 // 管理已连接的 MCP 服务实例（key 为服务名）
-pub type McpService = RunningService<RoleClient, ()>;
+pub type McpService = RunningService<RoleClient, McpClientHandler>;
 
-pub struct McpState(pub Arc<DashMap<String, McpService>>);
+/// 单个服务器的受监管信息：原始配置用于断线重连，状态用于前端展示
+#[derive(Debug, Clone)]
+pub struct ServerEntry {
+  pub config: McpServerConfig,
+  pub status: McpServerStatus,
+  pub last_error: Option<String>,
+  /// 连续重连失败次数（成功后清零）
+  pub attempts: u32,
+  /// 每次 mcp_connect 递增，用于让旧的监控任务自行退出
+  pub generation: u64,
+  /// 最近一次连接成功时使用的传输方式
+  pub transport: Option<String>,
+  /// 最近一次连接时服务器在 initialize 中返回的信息（名称、版本、协议版本、能力与 instructions）
+  pub server_info: Option<InitializeResult>,
+  /// 按 `config.limits` 创建的调用限制，重新 mcp_connect 时随配置一起替换
+  pub limiter: Arc<CallLimiter>,
+}
+
+pub struct McpState {
+  pub services: Arc<DashMap<String, McpService>>,
+  pub servers: Arc<DashMap<String, ServerEntry>>,
+  /// tools/resources/prompts 列表缓存
+  pub listings: Arc<DashMap<String, ServerListings>>,
+  /// 已订阅的资源 URI（按服务器），重连后自动重新订阅
+  pub subscriptions: Arc<DashMap<String, HashSet<String>>>,
+  /// 进行中的工具调用（key 为 callId）
+  pub pending_calls: Arc<DashMap<String, PendingCall>>,
+  /// 进行中调用的 progressToken（按服务器）到 callId 的映射，用于给进度事件附上 callId
+  pub progress_calls: Arc<DashMap<(String, ProgressToken), String>>,
+  /// 等待前端答复的采样请求
+  pub sampling: PendingReplies<McpSamplingResponse>,
+  /// 等待用户填写的 elicitation 请求（上下文为 requestedSchema）
+  pub elicitations: PendingReplies<McpElicitationResponse, serde_json::Value>,
+  /// 内置 MCP 服务器转交前端执行的知识库检索
+  pub kb_queries: PendingReplies<McpKbQueryResponse>,
+  /// stdio 子进程的 stderr 输出（断开后保留，便于排查）
+  pub stderr_logs: Arc<DashMap<String, StderrLog>>,
+  /// stdio 子进程的 pid（同时是其进程组 id），退出应用时用于清理残留进程
+  pub child_pids: Arc<DashMap<String, u32>>,
+  /// 工具调用审计数据库（首次使用时打开）
+  pub audit_db: OnceCell<SqlitePool>,
+}
 
 impl McpState {
   pub fn new() -> Self {
-    Self(Arc::new(DashMap::new()))
+    Self {
+      services: Arc::new(DashMap::new()),
+      servers: Arc::new(DashMap::new()),
+      listings: Arc::new(DashMap::new()),
+      subscriptions: Arc::new(DashMap::new()),
+      pending_calls: Arc::new(DashMap::new()),
+      progress_calls: Arc::new(DashMap::new()),
+      sampling: PendingReplies::new("sampling"),
+      elicitations: PendingReplies::new("elicitation"),
+      kb_queries: PendingReplies::new("kb"),
+      stderr_logs: Arc::new(DashMap::new()),
+      child_pids: Arc::new(DashMap::new()),
+      audit_db: OnceCell::new(),
+    }
+  }
+
+  /// 取出服务对应的 Peer（可克隆），避免在 await 期间持有 DashMap 的读锁
+  pub fn peer(&self, server_name: &str) -> Result<Peer<RoleClient>, String> {
+    self
+      .services
+      .get(server_name)
+      .map(|s| s.peer().clone())
+      .ok_or_else(|| "Server not found".to_string())
   }
 }
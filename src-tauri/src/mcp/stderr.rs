@@ -0,0 +1,144 @@
+use crate::mcp::state::McpState;
+use std::collections::VecDeque;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::ChildStderr;
+use tokio::time::{sleep, Duration};
+
+/// 每个服务器保留的 stderr 行数上限
+const MAX_LINES: usize = 500;
+/// 单行长度上限（字符），防止进度条等超长输出占满缓冲
+const MAX_LINE_CHARS: usize = 2000;
+/// 连接失败时附在错误信息后的行数
+const ERROR_TAIL_LINES: usize = 20;
+/// 进程退出后等待读取任务收尾的时间
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+/// stdio 子进程 stderr 的环形缓冲。每次启动进程开始一个新会话，
+/// 错误信息只附带本次会话的输出，避免混入上一次启动的日志。
+#[derive(Debug, Default)]
+pub struct StderrLog {
+  lines: VecDeque<(u64, String)>,
+  next_seq: u64,
+  session_start: u64,
+}
+
+impl StderrLog {
+  pub fn push(&mut self, line: String) {
+    let line = if line.chars().count() > MAX_LINE_CHARS {
+      let mut cut: String = line.chars().take(MAX_LINE_CHARS).collect();
+      cut.push('…');
+      cut
+    } else {
+      line
+    };
+    self.lines.push_back((self.next_seq, line));
+    self.next_seq += 1;
+    while self.lines.len() > MAX_LINES {
+      self.lines.pop_front();
+    }
+  }
+
+  pub fn begin_session(&mut self) {
+    self.session_start = self.next_seq;
+  }
+
+  /// 最近的 limit 行（跨会话）
+  pub fn recent(&self, limit: usize) -> Vec<String> {
+    let skip = self.lines.len().saturating_sub(limit);
+    self
+      .lines
+      .iter()
+      .skip(skip)
+      .map(|(_, l)| l.clone())
+      .collect()
+  }
+
+  /// 本次会话最后的 limit 行
+  pub fn session_tail(&self, limit: usize) -> Vec<String> {
+    let session: Vec<&String> = self
+      .lines
+      .iter()
+      .filter(|(seq, _)| *seq >= self.session_start)
+      .map(|(_, l)| l)
+      .collect();
+    let skip = session.len().saturating_sub(limit);
+    session.into_iter().skip(skip).cloned().collect()
+  }
+}
+
+/// 启动子进程前调用，标记新会话的起点
+pub(crate) fn begin_session(state: &McpState, server_name: &str) {
+  state
+    .stderr_logs
+    .entry(server_name.to_string())
+    .or_default()
+    .begin_session();
+}
+
+/// 后台逐行读取子进程 stderr，写入缓冲并同步到应用日志（debug 级）
+pub(crate) fn spawn_reader(app: AppHandle, server_name: String, stderr: ChildStderr) {
+  tauri::async_runtime::spawn(async move {
+    let mut reader = BufReader::new(stderr);
+    let mut buf = Vec::new();
+    loop {
+      buf.clear();
+      match reader.read_until(b'\n', &mut buf).await {
+        Ok(0) => break,
+        Ok(_) => {
+          let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+          if line.is_empty() {
+            continue;
+          }
+          log::debug!("[MCP/stderr/{}] {}", server_name, line);
+          let state = app.state::<McpState>();
+          state
+            .stderr_logs
+            .entry(server_name.clone())
+            .or_default()
+            .push(line);
+        }
+        Err(e) => {
+          log::debug!("[MCP/stderr/{}] Reader stopped: {}", server_name, e);
+          break;
+        }
+      }
+    }
+  });
+}
+
+/// 在连接错误后附上本次会话的 stderr 末尾若干行
+pub(crate) async fn with_tail(app: &AppHandle, server_name: &str, error: String) -> String {
+  sleep(SETTLE_DELAY).await;
+  let state = app.state::<McpState>();
+  let tail = state
+    .stderr_logs
+    .get(server_name)
+    .map(|l| l.session_tail(ERROR_TAIL_LINES))
+    .unwrap_or_default();
+  if tail.is_empty() {
+    error
+  } else {
+    format!("{}\n--- stderr ---\n{}", error, tail.join("\n"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_ring_buffer_and_session_tail() {
+    let mut log = StderrLog::default();
+    for i in 0..(MAX_LINES + 10) {
+      log.push(format!("old {}", i));
+    }
+    assert_eq!(log.recent(usize::MAX).len(), MAX_LINES);
+    assert_eq!(log.recent(1), vec![format!("old {}", MAX_LINES + 9)]);
+
+    log.begin_session();
+    assert!(log.session_tail(5).is_empty());
+    log.push("boom".into());
+    assert_eq!(log.session_tail(5), vec!["boom".to_string()]);
+  }
+}
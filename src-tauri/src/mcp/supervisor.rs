@@ -0,0 +1,275 @@
+use crate::mcp::cache;
+use crate::mcp::connect::connect_server;
+use crate::mcp::limits::CallLimiter;
+use crate::mcp::state::{McpState, ServerEntry};
+use crate::mcp::types::{McpServerConfig, McpServerStatus, McpServerStatusInfo};
+use rmcp::model::SubscribeRequestParam;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::time::{sleep, Duration};
+
+/// 前端监听的状态变更事件名
+pub const STATUS_EVENT: &str = "mcp-server-status";
+
+// —— 监控与重连参数 ——
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const BACKOFF_BASE_MS: u64 = 1_000;
+const BACKOFF_MAX_MS: u64 = 60_000;
+const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+
+static GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn status_info(name: &str, entry: &ServerEntry) -> McpServerStatusInfo {
+  McpServerStatusInfo {
+    server_name: name.to_string(),
+    status: entry.status,
+    error: entry.last_error.clone(),
+    attempts: entry.attempts,
+    transport: entry.transport.clone(),
+  }
+}
+
+/// 登记（或覆盖）一个受监管的服务器，返回本次连接的 generation
+pub(crate) fn register(
+  app: &AppHandle,
+  state: &McpState,
+  name: &str,
+  config: McpServerConfig,
+) -> u64 {
+  let generation = GENERATION.fetch_add(1, Ordering::SeqCst);
+  let limiter = Arc::new(CallLimiter::new(config.limits.as_ref()));
+  let entry = ServerEntry {
+    config,
+    status: McpServerStatus::Connecting,
+    last_error: None,
+    attempts: 0,
+    generation,
+    transport: None,
+    server_info: None,
+    limiter,
+  };
+  let info = status_info(name, &entry);
+  state.servers.insert(name.to_string(), entry);
+  let _ = app.emit(STATUS_EVENT, info);
+  generation
+}
+
+/// 撤销 `register` 的登记：仅当条目仍属于该 generation 时移除，并恢复登记前的条目（如有）
+pub(crate) fn unregister(
+  app: &AppHandle,
+  state: &McpState,
+  name: &str,
+  generation: u64,
+  previous: Option<ServerEntry>,
+) {
+  if state
+    .servers
+    .remove_if(name, |_, e| e.generation == generation)
+    .is_none()
+  {
+    return;
+  }
+  if let Some(previous) = previous {
+    let info = status_info(name, &previous);
+    state.servers.entry(name.to_string()).or_insert(previous);
+    let _ = app.emit(STATUS_EVENT, info);
+  }
+}
+
+/// 更新状态；仅在状态或错误信息发生变化时推送事件
+pub(crate) fn set_status(
+  app: &AppHandle,
+  state: &McpState,
+  name: &str,
+  status: McpServerStatus,
+  error: Option<String>,
+) {
+  let info = {
+    let Some(mut entry) = state.servers.get_mut(name) else {
+      return;
+    };
+    if entry.status == status && entry.last_error == error {
+      return;
+    }
+    entry.status = status;
+    entry.last_error = error;
+    status_info(name, &entry)
+  };
+  log::info!(
+    "[MCP/supervisor] Server {} -> {:?}{}",
+    name,
+    info.status,
+    info
+      .error
+      .as_ref()
+      .map(|e| format!(" ({})", e))
+      .unwrap_or_default()
+  );
+  let _ = app.emit(STATUS_EVENT, info);
+}
+
+/// 列出所有受监管服务器的当前状态
+pub(crate) fn snapshot(state: &McpState) -> Vec<McpServerStatusInfo> {
+  state
+    .servers
+    .iter()
+    .map(|e| status_info(e.key(), e.value()))
+    .collect()
+}
+
+fn is_current(state: &McpState, name: &str, generation: u64) -> bool {
+  state
+    .servers
+    .get(name)
+    .map(|e| e.generation == generation)
+    .unwrap_or(false)
+}
+
+/// 指数退避：1s, 2s, 4s ... 封顶 60s
+pub(crate) fn backoff_delay(attempt: u32) -> Duration {
+  let ms = BACKOFF_BASE_MS.saturating_mul(1u64 << attempt.min(16));
+  Duration::from_millis(ms.min(BACKOFF_MAX_MS))
+}
+
+/// 为已连接的服务器启动后台监控：检测传输关闭并自动重连。
+/// 当服务器被断开或以新的 generation 重新连接时，任务自行退出。
+pub(crate) fn spawn_monitor(app: AppHandle, name: String, generation: u64) {
+  tauri::async_runtime::spawn(async move {
+    loop {
+      sleep(HEALTH_CHECK_INTERVAL).await;
+      let state = app.state::<McpState>();
+      if !is_current(&state, &name, generation) {
+        log::debug!("[MCP/supervisor] Monitor for {} stopped", name);
+        break;
+      }
+
+      let closed = state
+        .services
+        .get(&name)
+        .map(|s| s.is_transport_closed())
+        .unwrap_or(true);
+      if !closed {
+        continue;
+      }
+
+      log::warn!("[MCP/supervisor] Transport closed for server {}", name);
+      // 子进程已退出，其 pid（即进程组号）可能被复用，退出清理时不能再按它结束进程组
+      state.child_pids.remove(&name);
+      if let Some((_, service)) = state.services.remove(&name) {
+        let _ = service.cancel().await;
+      }
+      if !reconnect(&app, &name, generation).await {
+        break;
+      }
+    }
+  });
+}
+
+/// 按退避策略重连；成功返回 true，放弃（或已被断开/替换）返回 false
+async fn reconnect(app: &AppHandle, name: &str, generation: u64) -> bool {
+  let state = app.state::<McpState>();
+  loop {
+    let (config, attempt) = match state.servers.get_mut(name) {
+      Some(mut e) if e.generation == generation => {
+        e.attempts += 1;
+        (e.config.clone(), e.attempts)
+      }
+      _ => return false,
+    };
+
+    if attempt > MAX_RECONNECT_ATTEMPTS {
+      set_status(
+        app,
+        &state,
+        name,
+        McpServerStatus::Dead,
+        Some(format!(
+          "Reconnect gave up after {} attempts",
+          MAX_RECONNECT_ATTEMPTS
+        )),
+      );
+      return false;
+    }
+
+    set_status(
+      app,
+      &state,
+      name,
+      McpServerStatus::Degraded,
+      Some(format!("Transport closed, reconnect attempt {}", attempt)),
+    );
+    sleep(backoff_delay(attempt - 1)).await;
+    if !is_current(&state, name, generation) {
+      return false;
+    }
+
+    // 首次连接时用户已确认过同一配置
+    match connect_server(app, name, &config, true).await {
+      Ok((service, transport)) => {
+        if !is_current(&state, name, generation) {
+          let _ = service.cancel().await;
+          return false;
+        }
+        let server_info = service.peer().peer_info().cloned();
+        state.services.insert(name.to_string(), service);
+        // 重连后服务器的工具等可能已变化
+        cache::invalidate_all(&state, name);
+        if let Some(mut e) = state.servers.get_mut(name) {
+          e.attempts = 0;
+          e.transport = Some(transport.to_string());
+          e.server_info = server_info;
+        }
+        resubscribe(&state, name).await;
+        set_status(app, &state, name, McpServerStatus::Ready, None);
+        return true;
+      }
+      Err(e) => {
+        log::warn!(
+          "[MCP/supervisor] Reconnect attempt {} for {} failed: {}",
+          attempt,
+          name,
+          e
+        );
+      }
+    }
+  }
+}
+
+/// 重连后恢复之前的资源订阅（新会话不保留旧订阅）
+async fn resubscribe(state: &McpState, name: &str) {
+  let uris: Vec<String> = state
+    .subscriptions
+    .get(name)
+    .map(|s| s.iter().cloned().collect())
+    .unwrap_or_default();
+  let Ok(peer) = state.peer(name) else {
+    return;
+  };
+  for uri in uris {
+    if let Err(e) = peer
+      .subscribe(SubscribeRequestParam { uri: uri.clone() })
+      .await
+    {
+      log::warn!(
+        "[MCP/supervisor] Failed to resubscribe {} on {}: {}",
+        uri,
+        name,
+        e
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_backoff_delay_grows_and_caps() {
+    assert_eq!(backoff_delay(0), Duration::from_secs(1));
+    assert_eq!(backoff_delay(3), Duration::from_secs(8));
+    assert_eq!(backoff_delay(10), Duration::from_secs(60));
+    assert_eq!(backoff_delay(u32::MAX), Duration::from_secs(60));
+  }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/// 各服务器工具调用策略的持久化文件（key 为服务器名）
+const TOOL_POLICY_STORE: &str = "mcp-tool-policies.json";
+/// 策略为 ask 且未确认时工具调用返回的错误前缀，前端询问用户后以 confirmed=true 重试
+pub const TOOL_APPROVAL_REQUIRED: &str = "MCP_TOOL_APPROVAL_REQUIRED";
+/// 策略为 deny 时的错误前缀
+pub const TOOL_DENIED: &str = "MCP_TOOL_DENIED";
+
+/// 单个工具的调用策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolPolicy {
+  /// 直接调用（默认，与历史行为一致）
+  #[default]
+  Allow,
+  /// 每次调用前需用户确认
+  Ask,
+  /// 一律拒绝
+  Deny,
+}
+
+/// 某服务器的策略：按工具名精确匹配，未列出的工具使用 `default`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpServerToolPolicies {
+  pub default: ToolPolicy,
+  pub tools: HashMap<String, ToolPolicy>,
+}
+
+impl McpServerToolPolicies {
+  pub fn policy_for(&self, tool_name: &str) -> ToolPolicy {
+    self.tools.get(tool_name).copied().unwrap_or(self.default)
+  }
+}
+
+/// 放行时记录到审计日志的决策来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+  Allowed,
+  Approved,
+}
+
+impl Decision {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Decision::Allowed => "allowed",
+      Decision::Approved => "approved",
+    }
+  }
+}
+
+pub(crate) fn load(app: &AppHandle, server_name: &str) -> McpServerToolPolicies {
+  let store = match app.store(TOOL_POLICY_STORE) {
+    Ok(s) => s,
+    Err(e) => {
+      log::warn!("[MCP/tool-policy] Failed to open tool policy store: {}", e);
+      return McpServerToolPolicies::default();
+    }
+  };
+  store
+    .get(server_name)
+    .and_then(|v| serde_json::from_value(v).ok())
+    .unwrap_or_default()
+}
+
+pub(crate) fn save(
+  app: &AppHandle,
+  server_name: &str,
+  policies: &McpServerToolPolicies,
+) -> Result<(), String> {
+  let store = app
+    .store(TOOL_POLICY_STORE)
+    .map_err(|e| format!("Failed to open tool policy store: {}", e))?;
+  let value = serde_json::to_value(policies).map_err(|e| e.to_string())?;
+  store.set(server_name, value);
+  store
+    .save()
+    .map_err(|e| format!("Failed to save tool policy store: {}", e))
+}
+
+/// 依据策略决定是否放行：deny 与未确认的 ask 返回带前缀的错误
+pub(crate) fn decide(
+  policy: ToolPolicy,
+  server_name: &str,
+  tool_name: &str,
+  confirmed: bool,
+) -> Result<Decision, String> {
+  match (policy, confirmed) {
+    (ToolPolicy::Allow, _) => Ok(Decision::Allowed),
+    (ToolPolicy::Ask, true) => Ok(Decision::Approved),
+    (ToolPolicy::Ask, false) => Err(format!(
+      "{}: {}/{}",
+      TOOL_APPROVAL_REQUIRED, server_name, tool_name
+    )),
+    (ToolPolicy::Deny, _) => Err(format!(
+      "{}: tool {} on server {} is denied by policy",
+      TOOL_DENIED, tool_name, server_name
+    )),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_policy_lookup_and_decision() {
+    let policies: McpServerToolPolicies = serde_json::from_value(serde_json::json!({
+      "default": "ask",
+      "tools": { "read_file": "allow", "delete_file": "deny" }
+    }))
+    .unwrap();
+    assert_eq!(policies.policy_for("read_file"), ToolPolicy::Allow);
+    assert_eq!(policies.policy_for("write_file"), ToolPolicy::Ask);
+
+    let ask = policies.policy_for("write_file");
+    assert!(decide(ask, "fs", "write_file", false)
+      .unwrap_err()
+      .starts_with(TOOL_APPROVAL_REQUIRED));
+    assert_eq!(
+      decide(ask, "fs", "write_file", true),
+      Ok(Decision::Approved)
+    );
+    let deny = policies.policy_for("delete_file");
+    assert!(decide(deny, "fs", "delete_file", true)
+      .unwrap_err()
+      .starts_with(TOOL_DENIED));
+  }
+}
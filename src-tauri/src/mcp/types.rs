@@ -1,17 +1,138 @@
+use rmcp::model::InitializeResult;
 use serde::{Deserialize, Serialize};
 
 // 前端传入的服务器配置（简化版）
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct McpServerConfig {
-  pub r#type: String,                         // stdio | sse | http
+  pub r#type: String,                         // stdio | sse | http | auto
   pub command: Option<String>,                // stdio 用
   pub args: Option<Vec<String>>,              // stdio 用
   pub env: Option<Vec<(String, String)>>,     // stdio 用（键值对）
+  /// 子进程工作目录；args / env / cwd / headers 均支持 ${VAR}、~ 与 ${secret:NAME} 展开
+  pub cwd: Option<String>,                    // stdio 用（可选）
+  /// 是否继承应用的环境变量（默认 true）；为 false 时仅保留 PATH、HOME 等基础变量
+  pub inherit_env: Option<bool>,              // stdio 用（可选）
   pub base_url: Option<String>,               // sse/http 用
   pub headers: Option<Vec<(String, String)>>, // sse/http 用
   /// 是否对该 MCP 连接启用自定义代理（前端可选传入；未传或为 false 则不启用）
   pub use_proxy: Option<bool>,                // sse/http 用（可选）
   /// 自定义代理地址（形如 http://127.0.0.1:7890），与 use_proxy 配合使用
   pub proxy_url: Option<String>,              // sse/http 用（可选）
+  /// 沙箱运行（仅 Linux，需安装 bubblewrap）；未传或 enabled=false 则照常启动
+  pub sandbox: Option<McpSandboxConfig>,      // stdio 用（可选）
+  /// 工具调用的并发与速率限制；未传则不限制
+  pub limits: Option<McpCallLimits>,          // 所有传输（可选）
+}
+
+/// 单个服务器的工具调用限制。超出时调用排队等待，等待超过 `max_wait_ms` 返回 `MCP_RATE_LIMITED` 错误
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpCallLimits {
+  /// 同时进行中的调用数上限
+  pub max_concurrent: Option<usize>,
+  /// 令牌桶补充速率（每秒调用数，可为小数，如 0.5 表示每 2 秒一次）
+  pub rate_per_second: Option<f64>,
+  /// 令牌桶容量（允许的突发调用数），默认为 rate_per_second 向上取整
+  pub burst: Option<u32>,
+  /// 排队等待的上限（毫秒），默认 30000
+  pub max_wait_ms: Option<u64>,
+}
+
+/// stdio 服务器的沙箱配置：仅系统目录只读可见，主目录与本应用的数据目录被隐藏，
+/// 已授权 roots 与 writablePaths 可写；默认断网，可选资源限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct McpSandboxConfig {
+  pub enabled: bool,
+  /// 允许网络访问；默认在独立的 network namespace 中断网运行
+  pub allow_network: bool,
+  /// 额外的只读目录（支持 ~/ 前缀），如主目录下的运行时或数据
+  pub readable_paths: Vec<String>,
+  /// 额外的可写目录（支持 ~/ 前缀），如 npx 需要的 ~/.npm
+  pub writable_paths: Vec<String>,
+  /// 地址空间上限（MB）；注意 Node/V8 会预留较大的虚拟内存
+  pub memory_limit_mb: Option<u64>,
+  /// CPU 时间上限（秒）
+  pub cpu_time_secs: Option<u64>,
+  /// 进程数上限（RLIMIT_NPROC）：统计的是该用户的全部进程与线程，须大于当前已有数量
+  pub max_processes: Option<u64>,
+  /// 打开文件数上限
+  pub max_open_files: Option<u64>,
+}
+
+/// MCP 服务器连接状态（由 supervisor 维护，并通过 `mcp-server-status` 事件推送给前端）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpServerStatus {
+  Connecting,
+  Ready,
+  /// 传输已断开，正在按退避策略重连
+  Degraded,
+  /// 连接失败或重连次数耗尽
+  Dead,
+}
+
+/// 推送给前端的状态变更事件 / 状态查询结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerStatusInfo {
+  pub server_name: String,
+  pub status: McpServerStatus,
+  pub error: Option<String>,
+  pub attempts: u32,
+  /// 实际使用的传输方式（stdio / sse / http），尚未连接成功时为空
+  pub transport: Option<String>,
+}
+
+/// `mcp_connect` 的返回值
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpConnectionInfo {
+  pub server_name: String,
+  /// 实际使用的传输方式；type 为 "auto" 时为探测结果
+  pub transport: String,
+  /// 服务器此前已连接，本次调用未建立新连接
+  pub already_connected: bool,
+}
+
+/// `mcp_get_server_info` 的返回值：服务器在 initialize 中返回的信息，
+/// 附带由 capabilities 推导出的功能开关，便于前端隐藏服务器未声明的功能
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerInfo {
+  pub server_name: String,
+  pub transport: Option<String>,
+  /// protocolVersion / capabilities / serverInfo / instructions
+  #[serde(flatten)]
+  pub initialize: InitializeResult,
+  pub supports_tools: bool,
+  pub supports_resources: bool,
+  pub supports_prompts: bool,
+  pub supports_logging: bool,
+  pub supports_completions: bool,
+}
+
+impl McpServerInfo {
+  pub fn new(server_name: String, transport: Option<String>, initialize: InitializeResult) -> Self {
+    let caps = &initialize.capabilities;
+    Self {
+      supports_tools: caps.tools.is_some(),
+      supports_resources: caps.resources.is_some(),
+      supports_prompts: caps.prompts.is_some(),
+      supports_logging: caps.logging.is_some(),
+      supports_completions: caps.completions.is_some(),
+      server_name,
+      transport,
+      initialize,
+    }
+  }
+}
+
+/// 前端提交的授权目录（本地路径或 file:// URI）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpRootInput {
+  pub path: String,
+  pub name: Option<String>,
 }
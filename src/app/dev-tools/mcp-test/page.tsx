@@ -217,11 +217,11 @@ export default function McpTestPage() {
           className="px-3 py-1 rounded bg-teal-700 text-white"
           onClick={async ()=>{
             try {
-              await invoke('start_local_mcp_sse', { address: '127.0.0.1:8788' });
-              alert('已启动本地最小 MCP SSE 服务：127.0.0.1:8788/sse');
+              const info = await invoke<{ url: string; token: string }>('start_local_mcp_sse', { address: '127.0.0.1:8788' });
+              alert(`已启动内置 MCP SSE 服务：${info.url}\nAuthorization: Bearer ${info.token}`);
             } catch(e){ alert('启动失败: '+ String(e)); }
           }}
-        >启动最小 MCP SSE 服务</button>
+        >启动内置 MCP SSE 服务</button>
       </div>
       <div className="grid grid-cols-1 md:grid-cols-4 gap-2">
         <label className="flex items-center gap-2">